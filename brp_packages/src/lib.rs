@@ -1,35 +1,341 @@
 use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
 
 const STANDARD_GRAVITATIONAL_PARAMETER: f32 = 3.986004418e5;
+const EARTH_RADIUS: f32 = 6371.0; // 地球半径(km)
+const SECONDS_PER_DAY: f32 = 86400.0;
 
-/// Add a Satellite
-/// todo: replace by  AddOrbit and AddSatellite
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AngleUnit {
+    #[serde(rename = "deg")]
+    Degree,
+    #[default]
+    #[serde(rename = "rad")]
+    Radian,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DistanceUnit {
+    #[default]
+    #[serde(rename = "km")]
+    Kilometer,
+    #[serde(rename = "m")]
+    Meter,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MeanMotionUnit {
+    #[serde(rename = "rev/day")]
+    RevolutionPerDay,
+    #[default]
+    #[serde(rename = "rad/s")]
+    RadianPerSecond,
+}
+
+/// Units of the values in a message, defaulting to the units of the simulator
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    pub angle: AngleUnit,
+    pub distance: DistanceUnit,
+    pub mean_motion: MeanMotionUnit,
+}
+
+impl Units {
+    fn angle_name(&self) -> &'static str {
+        match self.angle {
+            AngleUnit::Degree => "deg",
+            AngleUnit::Radian => "rad",
+        }
+    }
+
+    /// An angle in rad, and the full turn in the unit of the message
+    fn angle(&self, value: f32) -> (f32, f32) {
+        match self.angle {
+            AngleUnit::Degree => (value.to_radians(), 360.0),
+            AngleUnit::Radian => (value, TAU),
+        }
+    }
+
+    /// A distance in km
+    fn distance(&self, value: f32) -> f32 {
+        match self.distance {
+            DistanceUnit::Kilometer => value,
+            DistanceUnit::Meter => value / 1000.0,
+        }
+    }
+
+    /// A mean motion in rad/s
+    fn mean_motion(&self, value: f32) -> f32 {
+        match self.mean_motion {
+            MeanMotionUnit::RevolutionPerDay => value * TAU / SECONDS_PER_DAY,
+            MeanMotionUnit::RadianPerSecond => value,
+        }
+    }
+}
+
+/// A field of a message that failed validation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The same error, for a field nested in `parent`, e.g. `orbits[2].inclination`
+    pub fn within(self, parent: &str) -> Self {
+        Self {
+            field: format!("{}.{}", parent, self.field),
+            reason: self.reason,
+        }
+    }
+}
+
+/// Add an Orbit, without satellites
+///
+/// The size is given either by `apogee` and `perigee` (radii), or by `mean_motion` and
+/// `eccentricity`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct AddSatellite {
-    apogee: f32,
-    perigee: f32,
+pub struct AddOrbit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    apogee: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    perigee: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mean_motion: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    eccentricity: Option<f32>,
     inclination: f32,
     longitude_of_ascending_node: f32,
     argument_of_periapsis: f32,
-    mean_anomaly: f32,
+    #[serde(default)]
+    units: Units,
 }
 
-impl AddSatellite {
-    pub fn as_slice(&self) -> [f32; 6] {
-        // e = (apogee - perigee) / (apogee + perigee)
-        // mean_motion = (standard_gravitational_parameter /
-        //                ((apogee + perigee)/2) ** 3) ** 0.5
-        let e = (self.apogee - self.perigee) / (self.apogee + self.perigee);
-        let mean_motion = (STANDARD_GRAVITATIONAL_PARAMETER
-            / ((self.apogee + self.perigee) / 2.).powf(3.))
-        .sqrt();
-        [
-            mean_motion,
-            e,
-            self.inclination,
+impl AddOrbit {
+    /// Mean motion(rad/s), eccentricity, inclination, RAAN and argument of periapsis(rad), or
+    /// every field out of range
+    pub fn validate(&self) -> Result<[f32; 5], Vec<FieldError>> {
+        let units = &self.units;
+        let mut errors = vec![];
+
+        let size = match (
+            self.apogee,
+            self.perigee,
+            self.mean_motion,
+            self.eccentricity,
+        ) {
+            (Some(apogee), Some(perigee), None, None) => {
+                let (apogee, perigee) = (units.distance(apogee), units.distance(perigee));
+                if !perigee.is_finite() || perigee <= EARTH_RADIUS {
+                    errors.push(FieldError::new(
+                        "perigee",
+                        format!("must be above the Earth's surface ({} km)", EARTH_RADIUS),
+                    ));
+                }
+                if !apogee.is_finite() || apogee < perigee {
+                    errors.push(FieldError::new("apogee", "must not be below the perigee"));
+                }
+                // e = (apogee - perigee) / (apogee + perigee)
+                // mean_motion = (standard_gravitational_parameter /
+                //                ((apogee + perigee)/2) ** 3) ** 0.5
+                let e = (apogee - perigee) / (apogee + perigee);
+                let mean_motion =
+                    (STANDARD_GRAVITATIONAL_PARAMETER / ((apogee + perigee) / 2.).powf(3.)).sqrt();
+                Some((mean_motion, e))
+            }
+            (None, None, Some(mean_motion), Some(e)) => {
+                let mean_motion = units.mean_motion(mean_motion);
+                if !(0.0..1.0).contains(&e) {
+                    errors.push(FieldError::new("eccentricity", "must be in [0, 1)"));
+                } else if !mean_motion.is_finite() || mean_motion <= 0.0 {
+                    errors.push(FieldError::new("mean_motion", "must be positive"));
+                } else {
+                    // a = (standard_gravitational_parameter / mean_motion ** 2) ** (1/3)
+                    let semi_major_axis =
+                        (STANDARD_GRAVITATIONAL_PARAMETER / mean_motion.powi(2)).cbrt();
+                    if semi_major_axis * (1.0 - e) <= EARTH_RADIUS {
+                        errors.push(FieldError::new(
+                            "mean_motion",
+                            "too high, the perigee is below the Earth's surface",
+                        ));
+                    }
+                }
+                Some((mean_motion, e))
+            }
+            _ => {
+                errors.push(FieldError::new(
+                    "apogee",
+                    "either apogee and perigee, or mean_motion and eccentricity, are required",
+                ));
+                None
+            }
+        };
+
+        let (inclination, turn) = units.angle(self.inclination);
+        if !(0.0..=turn / 2.0).contains(&self.inclination) {
+            errors.push(FieldError::new(
+                "inclination",
+                format!("must be in [0, {}] {}", turn / 2.0, units.angle_name()),
+            ));
+        }
+        let raan = check_angle(
+            units,
+            "longitude_of_ascending_node",
             self.longitude_of_ascending_node,
+            &mut errors,
+        );
+        let argument_of_periapsis = check_angle(
+            units,
+            "argument_of_periapsis",
             self.argument_of_periapsis,
+            &mut errors,
+        );
+
+        match size {
+            Some((mean_motion, e)) if errors.is_empty() => {
+                Ok([mean_motion, e, inclination, raan, argument_of_periapsis])
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+/// An angle in rad, within one turn either way
+fn check_angle(units: &Units, field: &str, value: f32, errors: &mut Vec<FieldError>) -> f32 {
+    let (angle, turn) = units.angle(value);
+    if !(-turn..=turn).contains(&value) {
+        errors.push(FieldError::new(
+            field,
+            format!("must be in [-{}, {}] {}", turn, turn, units.angle_name()),
+        ));
+    }
+    angle
+}
+
+/// Attach a Satellite to an existing Orbit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttachSatellite {
+    pub id: String,
+    mean_anomaly: f32,
+    #[serde(default)]
+    units: Units,
+}
+
+impl AttachSatellite {
+    /// The mean anomaly(rad), or the field out of range
+    pub fn validate(&self) -> Result<f32, Vec<FieldError>> {
+        let mut errors = vec![];
+        let mean_anomaly = check_angle(&self.units, "mean_anomaly", self.mean_anomaly, &mut errors);
+        if errors.is_empty() {
+            Ok(mean_anomaly)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Add a Satellite on its own Orbit
+///
+/// The ID must be unique, which is left to the simulator to check.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddSatellite {
+    pub id: String,
+    #[serde(flatten)]
+    orbit: AddOrbit,
+    mean_anomaly: f32,
+}
+
+impl AddSatellite {
+    /// The orbital elements in the units of the simulator, with the mean anomaly(rad) last, or
+    /// every field out of range
+    pub fn validate(&self) -> Result<[f32; 6], Vec<FieldError>> {
+        let orbit = self.orbit.validate();
+        let mut errors = orbit.clone().err().unwrap_or_default();
+        let mean_anomaly = check_angle(
+            &self.orbit.units,
+            "mean_anomaly",
             self.mean_anomaly,
-        ]
+            &mut errors,
+        );
+        match orbit {
+            Ok([mean_motion, e, inclination, raan, argument_of_periapsis]) if errors.is_empty() => {
+                Ok([
+                    mean_motion,
+                    e,
+                    inclination,
+                    raan,
+                    argument_of_periapsis,
+                    mean_anomaly,
+                ])
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_validate() {
+        let satellite: AddSatellite = serde_json::from_value(serde_json::json!({
+            "id": "SAT-1",
+            "mean_motion": 15.0,
+            "eccentricity": 0.001,
+            "inclination": 53.0,
+            "longitude_of_ascending_node": -90.0,
+            "argument_of_periapsis": 0.0,
+            "mean_anomaly": 180.0,
+            "units": {"angle": "deg", "mean_motion": "rev/day"},
+        }))
+        .unwrap();
+        let elements = satellite.validate().unwrap();
+        assert!((elements[0] - 15.0 * TAU / 86400.0).abs() < 1e-9);
+        assert!((elements[2] - 53f32.to_radians()).abs() < 1e-6);
+        assert!((elements[5] - PI).abs() < 1e-6);
+
+        let satellite: AddSatellite = serde_json::from_value(serde_json::json!({
+            "id": "",
+            "apogee": 6_900_000.0,
+            "perigee": 6_000_000.0,
+            "inclination": 4.0,
+            "longitude_of_ascending_node": 0.0,
+            "argument_of_periapsis": 0.0,
+            "mean_anomaly": 0.0,
+            "units": {"distance": "m"},
+        }))
+        .unwrap();
+        let fields: Vec<String> = satellite
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+        assert_eq!(fields, ["perigee", "inclination"]);
+
+        let satellite: AttachSatellite = serde_json::from_value(serde_json::json!({
+            "id": "P0-1",
+            "mean_anomaly": 90.0,
+            "units": {"angle": "deg"},
+        }))
+        .unwrap();
+        assert!((satellite.validate().unwrap() - PI / 2.0).abs() < 1e-6);
+
+        let satellite: AttachSatellite = serde_json::from_value(serde_json::json!({
+            "id": "P0-2",
+            "mean_anomaly": 90.0,
+        }))
+        .unwrap();
+        assert_eq!(satellite.validate().unwrap_err()[0].field, "mean_anomaly");
     }
 }
@@ -2,11 +2,12 @@ use crate::prelude::*;
 
 use bevy::input::mouse::MouseWheel;
 
-#[cfg(target_os = "windows")]
+// Mouse drag and wheel everywhere but macOS, so Linux builds have a camera too.
+#[cfg(not(target_os = "macos"))]
 use bevy::input::mouse::{MouseButtonInput, MouseMotion};
-#[cfg(target_os = "windows")]
+#[cfg(not(target_os = "macos"))]
 const SCALE_FACTOR: f32 = -0.05;
-#[cfg(target_os = "windows")]
+#[cfg(not(target_os = "macos"))]
 const DRAG_FACTOR: f32 = 0.005;
 
 #[cfg(target_os = "macos")]
@@ -31,7 +32,7 @@ struct OrbitCamera {
     azimuthal_angle: f32, // 方位角（绕z轴的旋转）
     polar_angle: f32,     // 极角（与赤道夹角）
 
-    #[cfg(target_os = "windows")]
+    #[cfg(not(target_os = "macos"))]
     is_dragging: bool,
 }
 
@@ -44,20 +45,20 @@ fn setup(mut commands: Commands) {
             azimuthal_angle: 0.,
             polar_angle: 0.,
 
-            #[cfg(target_os = "windows")]
+            #[cfg(not(target_os = "macos"))]
             is_dragging: false,
         },
     ));
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_os = "macos"))]
 fn orbit_camera_system(
     mut mouse_motion_events: EventReader<MouseMotion>,
     mut mouse_button_input_events: EventReader<MouseButtonInput>,
     mut mouse_wheel_events: EventReader<MouseWheel>,
     mut query: Query<(&mut OrbitCamera, &mut Transform)>,
-) {
-    let (mut orbit_camera, mut transform) = query.single_mut();
+) -> Result {
+    let (mut orbit_camera, mut transform) = query.single_mut()?;
     // 处理鼠标按下和释放事件
     for event in mouse_button_input_events.read() {
         if event.button == MouseButton::Left {
@@ -97,6 +98,8 @@ fn orbit_camera_system(
     // 设置相机的位置，并使其始终朝向地球（即原点）
     transform.translation = Vec3::new(x, y, z);
     transform.look_at(Vec3::ZERO, Vec3::Z);
+
+    Ok(())
 }
 
 #[cfg(target_os = "macos")]
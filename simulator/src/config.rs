@@ -5,6 +5,7 @@ use std::{
 };
 
 use crate::prelude::*;
+use crate::tle::parse_tle;
 
 #[derive(Deserialize, Resource, Debug)]
 pub struct Config {
@@ -16,11 +17,54 @@ pub struct Config {
     pub simulation: Simulation,
     #[serde(rename = "Network")]
     pub network: Network,
+    #[serde(rename = "GroundStations", default)]
+    pub ground_stations: Vec<GroundStationConfig>,
+    #[serde(rename = "LinkBudget", default)]
+    pub link_budget: LinkBudgetConfig,
+    #[serde(rename = "Routing", default)]
+    pub routing: RoutingConfig,
+    #[serde(rename = "Traffic", default)]
+    pub traffic: TrafficConfig,
+    #[serde(rename = "Compute", default)]
+    pub compute: ComputeConfig,
+    #[serde(rename = "Power", default)]
+    pub power: PowerConfig,
+    #[serde(rename = "Recorder")]
+    pub recorder: Option<RecorderConfig>,
+    /// Text of the config file, kept to record it with the results.
+    #[serde(skip)]
+    pub source: String,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Dataset {
     constellation_file: String,
+    #[serde(default)]
+    format: Option<DatasetFormat>,
+    #[serde(default)]
+    pub propagator: Propagator,
+}
+
+/// File format of the constellation dataset.
+/// If not given, it is picked from the file extension.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetFormat {
+    /// JSON array of OMM records, as provided by CelesTrak.
+    Json,
+    /// Two-line or three-line element sets.
+    Tle,
+}
+
+/// How the satellites of a constellation are propagated.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Propagator {
+    /// Two-body keplerian motion.
+    #[default]
+    Kepler,
+    /// SGP4/SDP4, using the full OMM record (including `BSTAR`).
+    Sgp4,
 }
 
 #[derive(Deserialize, Default, Debug)]
@@ -29,13 +73,86 @@ pub struct Display {
     pub orbit: bool,
     // #[serde(default)]
     pub connection: bool,
+    #[serde(default)]
+    pub color_mode: ColorMode,
+}
+
+/// How satellites are coloured.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    #[default]
+    Default,
+    /// From red (empty battery) to green (full battery).
+    StateOfCharge,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Simulation {
+    /// Start time of the simulation in UTC, e.g. "2024-10-27T04:10:58". Defaults to now.
+    #[serde(default)]
+    pub start_time: Option<String>,
     pub time_speed: f32,
     pub connection_distance: f32,
     pub connection_number: usize,
+    /// How inter-satellite links are formed.
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub plus_grid: PlusGrid,
+    /// Links must pass above this altitude (km) over `EARTH_RADIUS`.
+    #[serde(default = "default_grazing_altitude")]
+    pub grazing_altitude: f32,
+    /// Seed of the random number generator, random if not given.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Apply J2 secular perturbations to two-body orbits.
+    #[serde(default)]
+    pub j2: bool,
+}
+
+/// Inter-satellite link topology.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    /// Link to the nearest satellites within `connection_distance`.
+    #[default]
+    Nearest,
+    /// Link to the in-plane predecessor and successor, and the nearest satellites in the
+    /// two adjacent planes.
+    PlusGrid,
+}
+
+/// Options of the `plus_grid` topology.
+#[derive(Deserialize, Debug)]
+pub struct PlusGrid {
+    /// Link the first and the last planes (sorted by RAAN).
+    #[serde(default)]
+    pub cross_seam: bool,
+    /// Shut down cross-plane links above this latitude (degrees).
+    #[serde(default)]
+    pub polar_latitude: Option<f32>,
+    /// Satellites whose inclinations and RAANs differ by less than this (degrees) share a plane.
+    #[serde(default = "default_plane_tolerance")]
+    pub plane_tolerance: f32,
+}
+
+impl Default for PlusGrid {
+    fn default() -> Self {
+        Self {
+            cross_seam: false,
+            polar_latitude: None,
+            plane_tolerance: default_plane_tolerance(),
+        }
+    }
+}
+
+fn default_plane_tolerance() -> f32 {
+    1.0
+}
+
+fn default_grazing_altitude() -> f32 {
+    100.0
 }
 
 #[derive(Deserialize, Debug)]
@@ -43,26 +160,348 @@ pub struct Network {
     pub port: u16,
 }
 
+/// Radio parameters of inter-satellite links, the same for every link.
+/// Ground links share them, so packets and task inputs cross a ground link at the capacity
+/// an ISL of the same length would have.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LinkBudgetConfig {
+    pub tx_power: f64,          // 发射功率(dBW)
+    pub tx_gain: f64,           // 发射天线增益(dBi)
+    pub rx_gain: f64,           // 接收天线增益(dBi)
+    pub frequency: f64,         // 载波频率(GHz)
+    pub bandwidth: f64,         // 带宽(MHz)
+    pub noise_temperature: f64, // 系统噪声温度(K)
+}
+
+impl Default for LinkBudgetConfig {
+    /// A Ka-band inter-satellite link.
+    fn default() -> Self {
+        Self {
+            tx_power: 10.0,
+            tx_gain: 38.0,
+            rx_gain: 38.0,
+            frequency: 26.0,
+            bandwidth: 500.0,
+            noise_temperature: 500.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub algorithm: RoutingAlgorithmKind,
+    /// Number of paths of `k_shortest_paths`.
+    pub k: usize,
+    /// Interval (s of simulation time) between routing table updates.
+    pub interval: f64,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: default(),
+            k: 3,
+            interval: 1.0,
+        }
+    }
+}
+
+/// Built-in routing algorithms.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingAlgorithmKind {
+    /// Dijkstra by hop count.
+    HopCount,
+    /// Dijkstra by propagation delay.
+    #[default]
+    Latency,
+    /// The `k` paths with the least propagation delay.
+    KShortestPaths,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TrafficConfig {
+    /// Size of the FIFO queue of each link direction.
+    pub queue_size: usize, // 队列长度(bytes)
+    pub flows: Vec<FlowConfig>,
+}
+
+impl Default for TrafficConfig {
+    fn default() -> Self {
+        Self {
+            queue_size: 1_000_000,
+            flows: vec![],
+        }
+    }
+}
+
+/// A traffic flow between two satellites or ground stations, by their names.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FlowConfig {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub generator: TrafficGenerator,
+    #[serde(default = "default_packet_size")]
+    pub packet_size: usize, // 包长(bytes)
+    /// Simulation time (s since the start) when the flow starts and stops.
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub stop: Option<f64>,
+}
+
+fn default_packet_size() -> usize {
+    1500
+}
+
+/// How packets of a flow are generated.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "generator", rename_all = "snake_case")]
+pub enum TrafficGenerator {
+    /// Poisson arrivals of `rate` packets per second.
+    Poisson { rate: f64 },
+    /// Packets of `packet_size` evenly spaced to send `bitrate` bit/s.
+    ConstantBitrate { bitrate: f64 },
+    /// Packets read from a CSV file of `time,size` lines: seconds since the flow starts, bytes.
+    Trace { trace_file: String },
+}
+
+/// Power system of every satellite.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PowerConfig {
+    pub battery_capacity: f64,  // 电池容量(Wh)
+    pub initial_soc: f64,       // 初始荷电状态(0~1)
+    pub solar_power: f64,       // 光照下太阳能板发电功率(W)
+    pub base_load: f64,         // 基础功耗(W)
+    pub isl_power: f64,         // 每条星间链路的功耗(W)
+    pub ground_link_power: f64, // 每条星地链路的功耗(W)
+    pub compute_power: f64,     // 运行计算任务时的功耗(W)
+    /// Satellites shed all links below this state of charge,
+    /// and link again once recharged to `restore_soc`.
+    pub shed_soc: f64,
+    pub restore_soc: f64,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            battery_capacity: 2000.0,
+            initial_soc: 1.0,
+            solar_power: 2500.0,
+            base_load: 800.0,
+            isl_power: 100.0,
+            ground_link_power: 150.0,
+            compute_power: 300.0,
+            shed_soc: 0.2,
+            restore_soc: 0.3,
+        }
+    }
+}
+
+/// Compute resources of every satellite, and the tasks offloaded to them.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ComputeConfig {
+    pub cpu_frequency: f64, // CPU频率(GHz)
+    pub memory: f64,        // 内存(MB)
+    pub policy: OffloadPolicyKind,
+    /// Number of hops searched by `least_loaded`.
+    pub k: usize,
+    pub tasks: Vec<TaskGeneratorConfig>,
+}
+
+impl Default for ComputeConfig {
+    fn default() -> Self {
+        Self {
+            cpu_frequency: 2.0,
+            memory: 4096.0,
+            policy: default(),
+            k: 2,
+            tasks: vec![],
+        }
+    }
+}
+
+/// Built-in offloading policies.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OffloadPolicyKind {
+    /// Run on the satellite where the task is generated.
+    #[default]
+    Local,
+    /// Run on the neighbour with the least propagation delay.
+    NearestNeighbour,
+    /// Run on the satellite with the least queued work within `k` hops.
+    LeastLoaded,
+    /// Wait for `assign_task` over the network interface.
+    External,
+}
+
+/// Poisson arrivals of tasks at a satellite or a ground station, by its name.
+/// Tasks of a ground station are offloaded from its linked satellite.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TaskGeneratorConfig {
+    pub source: String,
+    pub rate: f64,       // 任务到达率(tasks/s)
+    pub input_size: f64, // 输入数据量(MB)
+    pub cycles: f64,     // 所需CPU周期(Gcycles)
+    /// Deadline relative to the generation of each task.
+    pub deadline: f64, // (s)
+    #[serde(default)]
+    pub start: f64, // (s since the start)
+    #[serde(default)]
+    pub stop: Option<f64>, // (s since the start)
+}
+
+impl TaskGeneratorConfig {
+    fn validate(&self) -> Result {
+        if !(self.rate > 0.0 && self.rate.is_finite()) {
+            return Err(format!("Tasks of {}: rate must be positive", self.source).into());
+        }
+        for (name, value) in [
+            ("input_size", self.input_size),
+            ("cycles", self.cycles),
+            ("deadline", self.deadline),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(
+                    format!("Tasks of {}: {} must not be negative", self.source, name).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Time series written to disk while the simulation runs.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct RecorderConfig {
+    /// Directory of the output files, created if missing. Existing files are overwritten.
+    pub directory: String,
+    /// Interval (s of simulation time) between samples of positions and links.
+    pub interval: f64,
+    pub formats: Vec<RecordFormat>,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            directory: "records".to_string(),
+            interval: 10.0,
+            formats: vec![RecordFormat::Csv],
+        }
+    }
+}
+
+/// File formats of the recorder.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordFormat {
+    /// Comma-separated values, after a header of `#` comment lines.
+    Csv,
+    /// Arrow IPC files (Feather v2), with the header in the schema metadata.
+    Arrow,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroundStationConfig {
+    pub name: String,
+    pub latitude: f64,  // 纬度(degrees)
+    pub longitude: f64, // 经度(degrees)
+    #[serde(default)]
+    pub altitude: f64, // 海拔(km)
+    /// Minimum elevation of a visible satellite.
+    #[serde(default)]
+    pub min_elevation: f64, // (degrees)
+    #[serde(default)]
+    pub policy: LinkPolicy,
+    #[serde(default = "default_max_links")]
+    pub max_links: usize,
+}
+
+fn default_max_links() -> usize {
+    1
+}
+
+/// How a ground station picks satellites among the visible ones.
+/// Links are kept until the satellite goes below the elevation mask.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPolicy {
+    #[default]
+    HighestElevation,
+    /// The satellite that stays above the elevation mask the longest.
+    LongestVisibility,
+    Nearest,
+}
+
 impl Config {
     pub fn load(file_path: &Path) -> Result<Self> {
         let mut file = File::open(file_path).map_err(|_| "Config file not found.")?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
 
-        let config: Config = toml::from_str(&content)?;
+        let mut config: Config = toml::from_str(&content)?;
+        config.source = content;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Reject values the simulation cannot run with, so they fail at startup.
+    fn validate(&self) -> Result {
+        if let Some(start_time) = &self.simulation.start_time {
+            parse_time_from_str(start_time)
+                .map_err(|err| format!("Invalid start_time {:?}: {}", start_time, err))?;
+        }
+        for task in &self.compute.tasks {
+            task.validate()?;
+        }
+        Ok(())
+    }
 }
 
 impl Dataset {
+    pub fn constellation_file(&self) -> &str {
+        &self.constellation_file
+    }
+
     pub fn read_from_file(&self) -> Result<Vec<RawSatelliteData>> {
         info!("Reading dataset from file: {}", self.constellation_file);
         let file = File::open(&self.constellation_file).map_err(|_| "Dataset file not found.")?;
-        let reader = BufReader::new(file);
-        let satellites_data: Vec<RawSatelliteData> = serde_json::from_reader(reader)?;
+        let mut reader = BufReader::new(file);
+        let satellites_data: Vec<RawSatelliteData> = match self.format()? {
+            DatasetFormat::Json => serde_json::from_reader(reader)?,
+            DatasetFormat::Tle => {
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                parse_tle(&content)?
+            }
+        };
 
         Ok(satellites_data)
     }
+
+    fn format(&self) -> Result<DatasetFormat> {
+        if let Some(format) = self.format {
+            return Ok(format);
+        }
+        let extension = Path::new(&self.constellation_file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Ok(DatasetFormat::Json),
+            Some("tle" | "3le" | "txt") => Ok(DatasetFormat::Tle),
+            _ => Err("Unknown dataset format, please set `format` in [Dataset].".into()),
+        }
+    }
 }
 
 /// Example of SatelliteData:
@@ -92,15 +531,60 @@ pub struct RawSatelliteData {
     #[serde(rename = "EPOCH")]
     pub epoch: String,
     #[serde(rename = "MEAN_MOTION")]
-    pub mean_motion: f32, // (rev/day)
+    pub mean_motion: f64, // (rev/day)
     #[serde(rename = "ECCENTRICITY")]
-    pub eccentricity: f32,
+    pub eccentricity: f64,
     #[serde(rename = "INCLINATION")]
-    pub inclination: f32, // (degrees)
+    pub inclination: f64, // (degrees)
     #[serde(rename = "RA_OF_ASC_NODE")]
-    pub ra_of_asc_node: f32, // (degrees)
+    pub ra_of_asc_node: f64, // (degrees)
     #[serde(rename = "ARG_OF_PERICENTER")]
-    pub arg_of_pericenter: f32, // (degrees)
+    pub arg_of_pericenter: f64, // (degrees)
     #[serde(rename = "MEAN_ANOMALY")]
-    pub mean_anomaly: f32, // (degrees)
+    pub mean_anomaly: f64, // (degrees)
+    #[serde(rename = "BSTAR", default)]
+    pub bstar: f64, // (1/earth radii)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_tasks() {
+        let config = |task: &str| {
+            let config: Config = toml::from_str(&format!(
+                r#"
+                [Network]
+                port = 0
+                [Display]
+                orbit = false
+                connection = false
+                [Simulation]
+                time_speed = 1.0
+                connection_distance = 5000.0
+                connection_number = 4
+                [[Compute.tasks]]
+                source = "Beijing"
+                {}
+                "#,
+                task
+            ))
+            .unwrap();
+            config.validate()
+        };
+        let valid = "rate = 0.5\ninput_size = 2.0\ncycles = 1.0\ndeadline = 10.0";
+        assert!(config(valid).is_ok());
+        for invalid in [
+            "rate = 0.0\ninput_size = 2.0\ncycles = 1.0\ndeadline = 10.0",
+            "rate = -1.0\ninput_size = 2.0\ncycles = 1.0\ndeadline = 10.0",
+            "rate = nan\ninput_size = 2.0\ncycles = 1.0\ndeadline = 10.0",
+            "rate = inf\ninput_size = 2.0\ncycles = 1.0\ndeadline = 10.0",
+            "rate = 0.5\ninput_size = -2.0\ncycles = 1.0\ndeadline = 10.0",
+            "rate = 0.5\ninput_size = 2.0\ncycles = -1.0\ndeadline = 10.0",
+            "rate = 0.5\ninput_size = 2.0\ncycles = 1.0\ndeadline = -10.0",
+        ] {
+            assert!(config(invalid).is_err(), "{}", invalid);
+        }
+    }
 }
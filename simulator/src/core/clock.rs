@@ -0,0 +1,133 @@
+use chrono::Duration;
+
+use super::*;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>()
+            .add_systems(FixedFirst, tick_clock);
+    }
+}
+
+/// The absolute simulation time.
+/// Time dependent states are computed from `elapsed_secs`, the seconds since `epoch`,
+/// so they are reproducible and do not drift.
+#[derive(Resource, Debug)]
+pub struct SimulationClock {
+    epoch: DateTime<Utc>,
+    elapsed: f64, // seconds since epoch
+    time_speed: f64,
+    paused: bool,
+    pending_steps: u32,
+    jumps: u32, // 跳转次数
+}
+
+impl FromWorld for SimulationClock {
+    fn from_world(world: &mut World) -> Self {
+        let simulation = &world.resource::<Config>().simulation;
+        // start_time is validated when loading the config
+        let epoch = simulation
+            .start_time
+            .as_deref()
+            .map(|time| parse_time_from_str(time).expect("Invalid start_time"))
+            .unwrap_or_else(Utc::now);
+        Self::new(epoch, simulation.time_speed as f64)
+    }
+}
+
+impl SimulationClock {
+    pub fn new(epoch: DateTime<Utc>, time_speed: f64) -> Self {
+        Self {
+            epoch,
+            elapsed: 0.0,
+            time_speed,
+            paused: false,
+            pending_steps: 0,
+            jumps: 0,
+        }
+    }
+
+    /// The start time of the simulation.
+    pub fn epoch(&self) -> DateTime<Utc> {
+        self.epoch
+    }
+
+    /// The current simulation time.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.epoch + Duration::microseconds((self.elapsed * 1e6).round() as i64)
+    }
+
+    /// Seconds since the epoch.
+    pub fn elapsed_secs(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn time_speed(&self) -> f64 {
+        self.time_speed
+    }
+
+    /// Simulated seconds per real second, any finite value (negative runs backwards).
+    pub fn set_time_speed(&mut self, time_speed: f64) {
+        if time_speed.is_finite() {
+            self.time_speed = time_speed;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    /// Advance a single fixed tick while paused.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Number of jumps so far, for systems that must reset on discontinuous time.
+    pub fn jumps(&self) -> u32 {
+        self.jumps
+    }
+
+    /// Jump to an absolute time, which may be before the epoch.
+    pub fn jump_to(&mut self, time: DateTime<Utc>) {
+        self.jumps += 1;
+        let duration = time - self.epoch;
+        self.elapsed = duration
+            .num_microseconds()
+            .map(|us| us as f64 / 1e6)
+            .unwrap_or(duration.num_milliseconds() as f64 / 1e3);
+    }
+}
+
+fn tick_clock(mut clock: ResMut<SimulationClock>, time: Res<Time<Fixed>>) {
+    if clock.paused {
+        if clock.pending_steps == 0 {
+            return;
+        }
+        clock.pending_steps -= 1;
+    }
+    clock.elapsed += time.delta_secs_f64() * clock.time_speed;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jump_to() {
+        let epoch = parse_time_from_str("2024-10-27T04:10:58.101312").unwrap();
+        let mut clock = SimulationClock::new(epoch, 1.0);
+        let target = parse_time_from_str("2024-10-28T00:00:00").unwrap();
+        clock.jump_to(target);
+        assert_eq!(clock.now(), target);
+        assert_eq!(clock.epoch(), epoch);
+    }
+}
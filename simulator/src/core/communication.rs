@@ -1,6 +1,10 @@
-use rand::{rng, seq::SliceRandom};
+use rand::seq::SliceRandom;
 
-use super::satellite::Satellite;
+use super::{
+    ground_station::GroundLinks, is_headless, link_budget::*, link_log::*,
+    plus_grid::update_plus_grid, power::LowPower, rng::SimulationRng, satellite::Satellite,
+    spatial::*,
+};
 use crate::prelude::*;
 
 pub struct CommunicationPlugin;
@@ -13,18 +17,25 @@ impl Plugin for CommunicationPlugin {
             .add_event::<DisconnectAll>();
 
         // Gizmos for visualization
-        app.add_systems(Update, draw_connections);
+        if !is_headless(app) {
+            app.add_systems(Update, draw_connections);
+        }
 
-        // Functionality
+        // Functionality, chained to keep link events deterministic
         app.add_systems(
             FixedUpdate,
             (
-                mark_satellites_try_connect,
-                connect_nearest,
+                (mark_satellites_try_connect, connect_nearest)
+                    .chain()
+                    .run_if(topology_is(Topology::Nearest)),
                 handle_connection,
-                disconnect_farthest,
+                disconnect_farthest.run_if(topology_is(Topology::Nearest)),
                 handle_disconnection,
-            ),
+                update_plus_grid.run_if(topology_is(Topology::PlusGrid)),
+                update_link_budgets,
+            )
+                .chain()
+                .after(rebuild_spatial_index),
         );
 
         app.add_systems(
@@ -34,13 +45,25 @@ impl Plugin for CommunicationPlugin {
     }
 }
 
+fn topology_is(topology: Topology) -> impl Fn(Res<Config>) -> bool {
+    move |config: Res<Config>| config.simulation.topology == topology
+}
+
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 struct TryConnect;
 
 #[derive(Component, Default)]
+#[require(LinkBudgets)]
 pub struct Connections {
-    connections: Vec<Entity>,
+    pub(super) connections: Vec<Entity>,
+}
+
+impl Connections {
+    /// Satellites linked by ISLs.
+    pub fn satellites(&self) -> &[Entity] {
+        &self.connections
+    }
 }
 
 #[derive(Event)]
@@ -53,6 +76,7 @@ struct ConnectTwo {
 struct DisconnectTwo {
     from: Entity,
     to: Entity,
+    reason: LinkDownReason,
 }
 
 #[derive(Event)]
@@ -61,10 +85,12 @@ pub struct DisconnectAll;
 fn mark_satellites_try_connect(
     mut commands: Commands,
     config: Res<Config>,
-    satellites: Query<(Entity, &Connections), (With<Satellite>, Without<TryConnect>)>,
+    mut rng: ResMut<SimulationRng>,
+    satellites: Query<
+        (Entity, &Connections),
+        (With<Satellite>, Without<TryConnect>, Without<LowPower>),
+    >,
 ) {
-    let mut rng = rng();
-
     // debug
     // let empty_sats = satellites
     //     .iter()
@@ -91,7 +117,7 @@ fn mark_satellites_try_connect(
     let part_of_unfull_sats_num =
         unfull_satellites.len() / (config.simulation.connection_number + 1);
 
-    unfull_satellites.shuffle(&mut rng); // O(n)
+    unfull_satellites.shuffle(&mut **rng); // O(n)
     unfull_satellites.sort_unstable_by_key(|(_, c)| *c); // O(n * log(connection_number)) ~ O(n)
     for &(sat, _) in unfull_satellites.iter().take(part_of_unfull_sats_num) {
         commands.entity(sat).insert(TryConnect);
@@ -101,86 +127,93 @@ fn mark_satellites_try_connect(
 fn connect_nearest(
     config: Res<Config>,
     mut commands: Commands,
-    from_satellites: Query<
-        (Entity, &Connections, &GlobalTransform),
-        (With<Satellite>, With<TryConnect>),
-    >,
-    to_satellites: Query<
-        (Entity, &Connections, &GlobalTransform),
-        (With<Satellite>, Without<TryConnect>),
-    >,
+    index: Res<SpatialIndex>,
+    from_satellites: Query<(Entity, &Connections), (With<Satellite>, With<TryConnect>)>,
+    to_satellites: Query<&Connections, (With<Satellite>, Without<TryConnect>, Without<LowPower>)>,
     mut connections: EventWriter<ConnectTwo>,
 ) {
     // get configuration parameters
     let connection_num = config.simulation.connection_number;
     let connection_dist = config.simulation.connection_distance;
+    let min_radius = EARTH_RADIUS + config.simulation.grazing_altitude;
 
-    // get all sats which are trying to connect, and get their global positions
-    let from_sats_iter = from_satellites
-        .into_iter()
-        // global transform to global coordinates
-        .map(|(sat, conn, trans)| (sat, conn, trans.translation()));
-
-    let to_satellies_iter = to_satellites
-        .into_iter()
-        // filter out satellites that already saturate their connections
-        .filter(|(_, conn, _)| conn.connections.len() < connection_num)
-        // global transform to global coordinates
-        .map(|(sat, conn, trans)| (sat, conn, trans.translation()))
-        .collect::<Vec<_>>();
-
-    // find the first satellite and its connections
-    for (cur_sat, cur_conn, cur_pos) in from_sats_iter {
-        // get all other satellites within the connection distance
-        let mut other_satellites: Vec<_> = to_satellies_iter
-            .iter()
-            .map(|(s, c, t)| (s, c, t.distance_squared(cur_pos)))
-            .filter(|(_, _, t)| *t < connection_dist * connection_dist)
+    for (cur_sat, cur_conn) in &from_satellites {
+        // remove marker
+        commands.entity(cur_sat).remove::<TryConnect>();
+        let Some(cur_pos) = index.position(cur_sat) else {
+            continue;
+        };
+
+        // get all other satellites within the connection distance, not blocked by the Earth
+        let mut other_satellites: Vec<_> = index
+            .within(cur_pos, connection_dist)
+            .filter(|(other_sat, _)| {
+                // filter out satellites that already saturate their connections
+                to_satellites.get(*other_sat).is_ok_and(|conn| {
+                    conn.connections.len() < connection_num
+                        && !cur_conn.connections.contains(other_sat)
+                })
+            })
+            .filter(|(other_sat, _)| {
+                index
+                    .position(*other_sat)
+                    .is_some_and(|other_pos| line_of_sight(cur_pos, other_pos, min_radius))
+            })
             .collect();
         // sort by distance to the current satellite
-        other_satellites.sort_unstable_by(|a, b| a.2.total_cmp(&b.2));
+        other_satellites.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
 
-        let count = connection_num - cur_conn.connections.len();
-        for (&other_sat, _, _) in &other_satellites[..count.min(other_satellites.len())] {
+        let count = connection_num.saturating_sub(cur_conn.connections.len());
+        for &(other_sat, _) in other_satellites.iter().take(count) {
             connections.write(ConnectTwo {
                 from: cur_sat,
                 to: other_sat,
             });
         }
-        // remove marker
-        commands.entity(cur_sat).remove::<TryConnect>();
     }
 }
 
 fn disconnect_farthest(
     config: Res<Config>,
-    satellites: Query<(Entity, &Connections, &GlobalTransform), With<Satellite>>,
+    index: Res<SpatialIndex>,
+    satellites: Query<(Entity, &Connections), With<Satellite>>,
+    low_power: Query<(), With<LowPower>>,
     mut ev_break: EventWriter<DisconnectTwo>,
 ) {
+    let min_radius = EARTH_RADIUS + config.simulation.grazing_altitude;
     let mut batch = vec![];
-    for (sat, conns, trans) in &satellites {
-        let cur_loc = trans.translation();
-        for other_sat in conns.connections.clone() {
+    for (sat, conns) in &satellites {
+        let Some(cur_loc) = index.position(sat) else {
+            continue;
+        };
+        for &other_sat in &conns.connections {
             // guarantee not to break the same connection twice
             if sat > other_sat {
                 continue;
             }
 
-            let other_loc = satellites.get(other_sat).unwrap().2.translation();
+            let Some(other_loc) = index.position(other_sat) else {
+                continue;
+            };
             let dis_sq = other_loc.distance_squared(cur_loc);
-            // break the connection which exceeds the connection distance
-            if dis_sq
+            // break the connection which exceeds the connection distance, is blocked by the Earth,
+            // or has a satellite low on power
+            let reason = if low_power.contains(sat) || low_power.contains(other_sat) {
+                LinkDownReason::LowPower
+            } else if dis_sq
                 > config.simulation.connection_distance * config.simulation.connection_distance
             {
-                // ev_break.write(DisconnectTwo {
-                //     from: sat,
-                //     to: other_sat,
-                // });
-                batch.push(DisconnectTwo {
-                    from: sat,
-                    to: other_sat,
-                });
-            }
+                LinkDownReason::OutOfRange
+            } else if !line_of_sight(cur_loc, other_loc, min_radius) {
+                LinkDownReason::Occluded
+            } else {
+                continue;
+            };
+            batch.push(DisconnectTwo {
+                from: sat,
+                to: other_sat,
+                reason,
+            });
         }
     }
 
@@ -194,6 +227,7 @@ fn handle_connection(
     config: Res<Config>,
     mut satellites: Query<(Entity, &mut Connections), With<Satellite>>,
     mut connections: EventReader<ConnectTwo>,
+    mut link_events: LinkEvents,
 ) {
     for ConnectTwo { from, to } in connections.read() {
         // println!("Connected {} and {}", from, to);
@@ -207,6 +241,8 @@ fn handle_connection(
         let mut from_conn = satellites.get_mut(*from).unwrap().1;
         from_conn.connections.push(*to);
         assert!(from_conn.connections.len() <= config.simulation.connection_number);
+
+        link_events.up(*from, *to, LinkKind::Isl);
     }
 }
 
@@ -214,13 +250,16 @@ fn handle_connection(
 fn handle_disconnection(
     mut satellites: Query<(Entity, &mut Connections), With<Satellite>>,
     mut connections: EventReader<DisconnectTwo>,
+    mut link_events: LinkEvents,
 ) {
-    for DisconnectTwo { from, to } in connections.read() {
+    for DisconnectTwo { from, to, reason } in connections.read() {
         let mut from_conn = satellites.get_mut(*from).unwrap().1;
         from_conn.connections.retain(|&sat| sat != *to);
 
         let mut to_conn = satellites.get_mut(*to).unwrap().1;
         to_conn.connections.retain(|&sat| sat != *from);
+
+        link_events.down(*from, *to, *reason);
     }
 }
 
@@ -228,11 +267,14 @@ fn handle_disconnect_all(
     mut commands: Commands,
     mut e: EventReader<DisconnectAll>,
     mut satellites: Query<(Entity, &mut Connections), With<Satellite>>,
+    mut link_events: LinkEvents,
 ) {
     for _ in e.read() {
         // println!("Disconnecting all satellites");
         for (sat, mut conns) in &mut satellites {
-            conns.connections.clear();
+            for other_sat in conns.connections.drain(..) {
+                link_events.down(sat, other_sat, LinkDownReason::Manual);
+            }
             commands.entity(sat).remove::<TryConnect>();
         }
     }
@@ -244,10 +286,30 @@ fn draw_connections(
     config: Res<Config>,
     mut gizmos: Gizmos,
     satellites: Query<(Entity, &Connections, &GlobalTransform), With<Satellite>>,
+    ground_stations: Query<(&GroundLinks, &GlobalTransform)>,
+    satellite_transforms: Query<&GlobalTransform, With<Satellite>>,
 ) {
     if !config.display.connection {
         return;
     }
+    for (links, global_trans) in &ground_stations {
+        let start = global_trans.translation();
+        for sat in &links.satellites {
+            let Ok(end) = satellite_transforms.get(*sat) else {
+                continue;
+            };
+            gizmos.line(
+                start,
+                end.translation(),
+                Srgba {
+                    red: 0.0,
+                    green: 1.0,
+                    blue: 1.0,
+                    alpha: 0.6,
+                },
+            );
+        }
+    }
     for (e, connections, global_trans) in &satellites {
         let start = global_trans.translation();
         for other_sat in &connections.connections {
@@ -271,6 +333,9 @@ fn draw_connections(
 
 #[cfg(test)]
 mod test {
+    use bevy::ecs::entity::EntityHashMap;
+
+    use super::{super::test_app, *};
 
     #[test]
     fn test_iter() {
@@ -281,4 +346,55 @@ mod test {
 
         println!("first: {}, rest: {:?}", first, rest); // first: 1, rest: [2, 3, 4, 5, 6]
     }
+
+    #[test]
+    fn test_reproducible_links() {
+        const CONFIG: &str = r#"
+            [Dataset]
+            constellation_file = "./Iridium.json"
+            [Network]
+            port = 0
+            [Display]
+            orbit = false
+            connection = false
+            [Simulation]
+            start_time = "2025-05-01T00:00:00"
+            time_speed = 600.0
+            connection_distance = 4000.0
+            connection_number = 4
+            seed = 42
+        "#;
+        const STEPS: u32 = 240;
+
+        // the same fixed steps, paced by one or by two steps per frame
+        let run = |steps_per_update: u32| {
+            let mut app = test_app(CONFIG, steps_per_update);
+            // the first update only starts the clocks
+            for _ in 0..=STEPS / steps_per_update {
+                app.update();
+            }
+            let elapsed = app.world().resource::<SimulationClock>().elapsed_secs();
+            let world = app.world_mut();
+            let names: EntityHashMap<String> = world
+                .query::<(Entity, &Name)>()
+                .iter(world)
+                .map(|(entity, name)| (entity, name.to_string()))
+                .collect();
+            let mut links: Vec<(String, Vec<String>)> = world
+                .query::<(&Name, &Connections)>()
+                .iter(world)
+                .map(|(name, conns)| {
+                    let others = conns.connections.iter().map(|e| names[e].clone());
+                    (name.to_string(), others.collect())
+                })
+                .collect();
+            links.sort();
+            (elapsed, links)
+        };
+
+        let (elapsed, links) = run(1);
+        assert!(elapsed > 0.0);
+        assert!(links.iter().any(|(_, others)| !others.is_empty()));
+        assert_eq!((elapsed, links), run(2));
+    }
 }
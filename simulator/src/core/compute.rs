@@ -0,0 +1,570 @@
+use bevy::ecs::entity::EntityHashMap;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use super::{ground_station::GroundLinks, link_budget::LinkBudget, *};
+
+pub struct ComputePlugin;
+
+impl Plugin for ComputePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Offloading>()
+            .add_systems(FixedUpdate, insert_compute_nodes)
+            .add_systems(
+                FixedPostUpdate,
+                (reset_on_jump, generate_tasks, receive_tasks, run_tasks)
+                    .chain()
+                    .after(update_routing),
+            );
+    }
+}
+
+/// Compute resources of a satellite. Tasks run one at a time, in arrival order.
+#[derive(Component, Debug)]
+pub struct ComputeNode {
+    pub cpu_frequency: f64, // CPU频率(Hz)
+    pub memory: f64,        // 内存(bytes)
+    used_memory: f64,       // 队列中任务占用的内存(bytes)
+    queue: VecDeque<QueuedTask>,
+}
+
+#[derive(Debug)]
+struct QueuedTask {
+    entity: Entity,
+    input_size: f64, // 输入数据量(bytes)
+    ready: f64,      // 到达时间(s)
+    deadline: f64,   // 截止时间(s)
+}
+
+impl ComputeNode {
+    fn new(config: &ComputeConfig) -> Self {
+        Self {
+            cpu_frequency: config.cpu_frequency * 1e9, // GHz to Hz
+            memory: config.memory * 1e6,               // MB to bytes
+            used_memory: 0.0,
+            queue: default(),
+        }
+    }
+
+    pub(super) fn is_busy(&self) -> bool {
+        !self.queue.is_empty()
+    }
+}
+
+/// A computation task, running on a `ComputeNode` or on its way there.
+#[derive(Component, Debug, Clone)]
+#[require(TaskState)]
+pub struct Task {
+    pub source: Entity,    // 产生任务的卫星或地面站
+    pub origin: Entity,    // 产生任务或接入的卫星
+    pub input_size: f64,   // 输入数据量(bytes)
+    pub cycles: f64,       // 所需CPU周期
+    pub created: f64,      // 产生时间(s)
+    pub deadline: f64,     // 截止时间(s)
+    remaining_cycles: f64, // 剩余CPU周期
+}
+
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub enum TaskState {
+    /// Waiting for a decision of the offloading policy.
+    #[default]
+    Pending,
+    /// Sent over the network to `node`.
+    Transferring {
+        node: Entity,
+        arrival: f64,
+    },
+    Queued {
+        node: Entity,
+    },
+}
+
+/// What an offloading policy sees of the network.
+pub struct ComputeView<'a> {
+    pub graph: &'a NetworkGraph,
+    /// Queued and incoming work of each compute node, in seconds.
+    pub loads: &'a EntityHashMap<f64>,
+}
+
+/// Decides where each task runs.
+/// Implement it and call `Offloading::set_policy` to use a custom one.
+pub trait OffloadPolicy: Send + Sync {
+    fn name(&self) -> String;
+
+    /// The compute node to run the task on, or `None` to leave it pending.
+    fn decide(&self, task: &Task, view: &ComputeView) -> Option<Entity>;
+}
+
+pub struct Local;
+
+impl OffloadPolicy for Local {
+    fn name(&self) -> String {
+        "local".to_string()
+    }
+
+    fn decide(&self, task: &Task, _view: &ComputeView) -> Option<Entity> {
+        Some(task.origin)
+    }
+}
+
+/// The linked satellite with the least propagation delay, or local if there is none.
+pub struct NearestNeighbour;
+
+impl OffloadPolicy for NearestNeighbour {
+    fn name(&self) -> String {
+        "nearest_neighbour".to_string()
+    }
+
+    fn decide(&self, task: &Task, view: &ComputeView) -> Option<Entity> {
+        let nearest = view
+            .graph
+            .neighbours(task.origin)
+            .filter(|(node, _)| view.loads.contains_key(node))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(node, _)| node);
+        Some(nearest.unwrap_or(task.origin))
+    }
+}
+
+/// The compute node with the least queued work within `k` hops, the nearest on ties.
+pub struct LeastLoaded {
+    pub k: usize,
+}
+
+impl OffloadPolicy for LeastLoaded {
+    fn name(&self) -> String {
+        format!("least_loaded(k={})", self.k)
+    }
+
+    fn decide(&self, task: &Task, view: &ComputeView) -> Option<Entity> {
+        let least_loaded = view
+            .graph
+            .within_hops(task.origin, self.k)
+            .into_iter()
+            .filter_map(|node| Some((node, *view.loads.get(&node)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(node, _)| node);
+        Some(least_loaded.unwrap_or(task.origin))
+    }
+}
+
+/// Leave every task pending, for `assign_task` over the network interface.
+pub struct External;
+
+impl OffloadPolicy for External {
+    fn name(&self) -> String {
+        "external".to_string()
+    }
+
+    fn decide(&self, _task: &Task, _view: &ComputeView) -> Option<Entity> {
+        None
+    }
+}
+
+/// Build a built-in offloading policy from the config.
+fn policy_from_config(kind: OffloadPolicyKind, k: usize) -> Box<dyn OffloadPolicy> {
+    match kind {
+        OffloadPolicyKind::Local => Box::new(Local),
+        OffloadPolicyKind::NearestNeighbour => Box::new(NearestNeighbour),
+        OffloadPolicyKind::LeastLoaded => Box::new(LeastLoaded { k }),
+        OffloadPolicyKind::External => Box::new(External),
+    }
+}
+
+/// Statistics of all tasks since the start of the simulation.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ComputeStats {
+    pub generated: u64,
+    pub completed: u64,
+    pub deadline_missed: u64,
+    /// Tasks without a route to their node, or that did not fit in its memory.
+    pub dropped: u64,
+    pub mean_latency: Option<f64>, // 平均完成时延(s)
+    #[serde(skip)]
+    total_latency: f64,
+}
+
+struct TaskGenerator {
+    config: TaskGeneratorConfig,
+    next_time: f64,         // 下一个任务的产生时间(s)
+    source: Option<Entity>, // 按名字找到的来源
+}
+
+/// Offloading of computation tasks to satellites.
+#[derive(Resource)]
+pub struct Offloading {
+    policy: Box<dyn OffloadPolicy>,
+    generators: Vec<TaskGenerator>,
+    stats: ComputeStats,
+    time: f64,  // 已模拟到的仿真时间(s)
+    jumps: u32, // 已处理的时钟跳转次数
+}
+
+impl FromWorld for Offloading {
+    fn from_world(world: &mut World) -> Self {
+        let config = &world.resource::<Config>().compute;
+        Self {
+            policy: policy_from_config(config.policy, config.k),
+            generators: config
+                .tasks
+                .iter()
+                .map(|config| TaskGenerator {
+                    config: config.clone(),
+                    next_time: config.start,
+                    source: None,
+                })
+                .collect(),
+            stats: default(),
+            time: 0.0,
+            jumps: 0,
+        }
+    }
+}
+
+impl Offloading {
+    pub fn policy(&self) -> String {
+        self.policy.name()
+    }
+
+    /// Switch the policy, for tasks generated from now on.
+    pub fn set_policy(&mut self, policy: Box<dyn OffloadPolicy>) {
+        info!("Offloading policy: {}", policy.name());
+        self.policy = policy;
+    }
+
+    pub fn set_policy_from_config(&mut self, kind: OffloadPolicyKind, k: usize) {
+        self.set_policy(policy_from_config(kind, k));
+    }
+
+    pub fn stats(&self) -> ComputeStats {
+        ComputeStats {
+            mean_latency: (self.stats.completed > 0)
+                .then(|| self.stats.total_latency / self.stats.completed as f64),
+            ..self.stats.clone()
+        }
+    }
+}
+
+/// Time to send `size` bytes along the nodes, hop by hop at the Shannon capacity of each link.
+fn transfer_time(
+    nodes: &[Entity],
+    size: f64,
+    graph: &NetworkGraph,
+    link_budget: &LinkBudgetConfig,
+) -> Option<f64> {
+    nodes
+        .windows(2)
+        .map(|pair| {
+            let delay = graph.delay(pair[0], pair[1])?;
+            let capacity = LinkBudget::new(link_budget, delay * network::SPEED_OF_LIGHT).capacity;
+            Some(size * 8.0 / capacity + delay)
+        })
+        .sum()
+}
+
+/// Send a task from its source to `node` along the current route.
+/// `None` if there is no route.
+pub fn dispatch_task(
+    task: &Task,
+    node: Entity,
+    now: f64,
+    routing: &mut Routing,
+    graph: &NetworkGraph,
+    link_budget: &LinkBudgetConfig,
+) -> Option<TaskState> {
+    let time = if task.source == node {
+        0.0
+    } else {
+        let path = routing.route(task.source, node).first()?;
+        transfer_time(&path.nodes, task.input_size, graph, link_budget)?
+    };
+    Some(TaskState::Transferring {
+        node,
+        arrival: now + time,
+    })
+}
+
+fn insert_compute_nodes(
+    mut commands: Commands,
+    config: Res<Config>,
+    satellites: Query<Entity, (With<Satellite>, Without<ComputeNode>)>,
+) {
+    for sate in &satellites {
+        commands
+            .entity(sate)
+            .insert(ComputeNode::new(&config.compute));
+    }
+}
+
+/// Discard all tasks when the clock jumps or runs backwards.
+fn reset_on_jump(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut offloading: ResMut<Offloading>,
+    mut nodes: Query<&mut ComputeNode>,
+    tasks: Query<Entity, With<Task>>,
+) {
+    let now = clock.elapsed_secs();
+    if clock.jumps() == offloading.jumps && now >= offloading.time {
+        return;
+    }
+    for task in &tasks {
+        commands.entity(task).despawn();
+    }
+    for mut node in &mut nodes {
+        node.queue.clear();
+        node.used_memory = 0.0;
+    }
+    for generator in &mut offloading.generators {
+        generator.next_time = generator.config.start.max(now);
+    }
+    offloading.jumps = clock.jumps();
+    offloading.time = now;
+}
+
+/// Generate tasks until now, and offload them by the policy.
+#[allow(clippy::too_many_arguments)]
+fn generate_tasks(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    config: Res<Config>,
+    graph: Res<NetworkGraph>,
+    mut routing: ResMut<Routing>,
+    mut offloading: ResMut<Offloading>,
+    mut rng: ResMut<SimulationRng>,
+    names: Query<(Entity, &Name)>,
+    ground_links: Query<&GroundLinks>,
+    nodes: Query<(Entity, &ComputeNode)>,
+    tasks: Query<(&Task, &TaskState)>,
+) {
+    let now = clock.elapsed_secs();
+    let Offloading {
+        policy,
+        generators,
+        stats,
+        ..
+    } = &mut *offloading;
+
+    for generator in generators.iter_mut().filter(|g| g.source.is_none()) {
+        generator.source = names
+            .iter()
+            .find(|(_, name)| name.as_str() == generator.config.source)
+            .map(|(entity, _)| entity);
+    }
+
+    let mut loads: Option<EntityHashMap<f64>> = None;
+    for generator in generators.iter_mut() {
+        let until = generator.config.stop.map_or(now, |stop| stop.min(now));
+        while generator.next_time < until {
+            let created = generator.next_time;
+            generator.next_time += -(1.0 - rng.random::<f64>()).ln() / generator.config.rate;
+            // skipped until the source exists
+            let Some(source) = generator.source else {
+                continue;
+            };
+            stats.generated += 1;
+            // tasks of a ground station start at its linked satellite
+            let origin = if nodes.contains(source) {
+                Some(source)
+            } else {
+                ground_links
+                    .get(source)
+                    .ok()
+                    .and_then(|links| links.satellites.first().copied())
+            };
+            let Some(origin) = origin else {
+                stats.dropped += 1;
+                continue;
+            };
+            let cycles = generator.config.cycles * 1e9; // Gcycles to cycles
+            let task = Task {
+                source,
+                origin,
+                input_size: generator.config.input_size * 1e6, // MB to bytes
+                cycles,
+                created,
+                deadline: created + generator.config.deadline,
+                remaining_cycles: cycles,
+            };
+
+            let current_loads = loads.get_or_insert_with(|| {
+                let mut loads: EntityHashMap<f64> =
+                    nodes.iter().map(|(node, _)| (node, 0.0)).collect();
+                // tasks on their way to a node count as well, as they will queue there
+                for (task, state) in &tasks {
+                    let (TaskState::Transferring { node, .. } | TaskState::Queued { node }) = state
+                    else {
+                        continue;
+                    };
+                    if let (Some(load), Ok((_, compute))) = (loads.get_mut(node), nodes.get(*node))
+                    {
+                        *load += task.remaining_cycles / compute.cpu_frequency;
+                    }
+                }
+                loads
+            });
+            let view = ComputeView {
+                graph: &graph,
+                loads: current_loads,
+            };
+            let state = match policy.decide(&task, &view) {
+                Some(node) => {
+                    let Some(state) = dispatch_task(
+                        &task,
+                        node,
+                        created,
+                        &mut routing,
+                        &graph,
+                        &config.link_budget,
+                    ) else {
+                        stats.dropped += 1;
+                        continue;
+                    };
+                    // count the work right away, so tasks in a burst spread out
+                    if let (Some(load), Ok((_, compute))) =
+                        (current_loads.get_mut(&node), nodes.get(node))
+                    {
+                        *load += task.cycles / compute.cpu_frequency;
+                    }
+                    state
+                }
+                None => TaskState::Pending,
+            };
+            commands.spawn((task, state));
+        }
+    }
+}
+
+/// Queue tasks that arrived at their nodes.
+fn receive_tasks(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut offloading: ResMut<Offloading>,
+    mut nodes: Query<&mut ComputeNode>,
+    mut tasks: Query<(Entity, &Task, &mut TaskState)>,
+) {
+    let now = clock.elapsed_secs();
+    for (entity, task, mut state) in &mut tasks {
+        let TaskState::Transferring { node, arrival } = *state else {
+            continue;
+        };
+        if arrival > now {
+            continue;
+        }
+        let Ok(mut compute) = nodes.get_mut(node) else {
+            offloading.stats.dropped += 1;
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if compute.used_memory + task.input_size > compute.memory {
+            offloading.stats.dropped += 1;
+            commands.entity(entity).despawn();
+            continue;
+        }
+        compute.used_memory += task.input_size;
+        compute.queue.push_back(QueuedTask {
+            entity,
+            input_size: task.input_size,
+            ready: arrival,
+            deadline: task.deadline,
+        });
+        *state = TaskState::Queued { node };
+    }
+}
+
+/// Run the queued tasks of every node until now. Tasks are aborted at their deadline.
+fn run_tasks(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut offloading: ResMut<Offloading>,
+    mut nodes: Query<&mut ComputeNode>,
+    mut tasks: Query<(Entity, &mut Task, &TaskState)>,
+) {
+    let now = clock.elapsed_secs();
+    let start = offloading.time;
+    let stats = &mut offloading.stats;
+
+    for (entity, task, state) in &tasks {
+        if !matches!(state, TaskState::Queued { .. }) && task.deadline < now {
+            stats.deadline_missed += 1;
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for mut node in &mut nodes {
+        let node = &mut *node;
+        let mut time = start;
+        while let Some(queued) = node.queue.front() {
+            time = time.max(queued.ready);
+            let Ok((entity, mut task, _)) = tasks.get_mut(queued.entity) else {
+                node.used_memory -= queued.input_size;
+                node.queue.pop_front();
+                continue;
+            };
+            let finish = time + task.remaining_cycles / node.cpu_frequency;
+            if finish > now && task.deadline >= now {
+                task.remaining_cycles -= (now - time).max(0.0) * node.cpu_frequency;
+                break;
+            }
+            if finish > task.deadline {
+                stats.deadline_missed += 1;
+                time = task.deadline.max(time);
+            } else {
+                stats.completed += 1;
+                stats.total_latency += finish - task.created;
+                time = finish;
+            }
+            node.used_memory -= queued.input_size;
+            node.queue.pop_front();
+            commands.entity(entity).despawn();
+        }
+        // waiting tasks past their deadline
+        node.queue.retain(|queued| {
+            if queued.deadline >= now {
+                return true;
+            }
+            stats.deadline_missed += 1;
+            node.used_memory -= queued.input_size;
+            commands.entity(queued.entity).despawn();
+            false
+        });
+    }
+    offloading.time = now;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let [a, b, c, d] = [0, 1, 2, 3].map(Entity::from_raw);
+        let mut graph = NetworkGraph::default();
+        graph.link(a, b, 0.002);
+        graph.link(a, c, 0.001);
+        graph.link(c, d, 0.001);
+        let loads: EntityHashMap<f64> = [(a, 3.0), (b, 2.0), (c, 1.0), (d, 0.5)]
+            .into_iter()
+            .collect();
+        let view = ComputeView {
+            graph: &graph,
+            loads: &loads,
+        };
+        let task = Task {
+            source: a,
+            origin: a,
+            input_size: 1e6,
+            cycles: 1e9,
+            created: 0.0,
+            deadline: 1.0,
+            remaining_cycles: 1e9,
+        };
+
+        assert_eq!(Local.decide(&task, &view), Some(a));
+        assert_eq!(NearestNeighbour.decide(&task, &view), Some(c));
+        assert_eq!(LeastLoaded { k: 1 }.decide(&task, &view), Some(c));
+        assert_eq!(LeastLoaded { k: 2 }.decide(&task, &view), Some(d));
+        assert_eq!(External.decide(&task, &view), None);
+    }
+}
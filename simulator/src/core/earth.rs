@@ -0,0 +1,138 @@
+use bevy::math::{DQuat, DVec3};
+use std::f64::consts::TAU;
+
+use super::*;
+
+const WGS84_A: f64 = 6378.137; // WGS-84 赤道半径(km)
+const WGS84_F: f64 = 1.0 / 298.257_223_563; // WGS-84 扁率
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F); // 第一偏心率的平方
+const EARTH_ROTATION_RATE: f64 = 7.292_115_146_706_979e-5; // 地球自转角速度(rad/s)
+
+pub struct EarthPlugin;
+
+impl Plugin for EarthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, rotate_earth);
+    }
+}
+
+/// The Earth, rotated from the Earth-fixed frame (ECEF) to the scene frame (ECI).
+/// Children are placed in ECEF.
+#[derive(Component)]
+#[require(Transform)]
+pub struct Earth;
+
+/// Geodetic coordinates on the WGS-84 ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geodetic {
+    pub latitude: f64,  // 纬度(rad)
+    pub longitude: f64, // 经度(rad)
+    pub altitude: f64,  // 海拔(km)
+}
+
+pub fn julian_date(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5
+}
+
+/// Greenwich mean sidereal time (IAU-82) in rad, UT1 is approximated by UTC.
+pub fn gmst(time: DateTime<Utc>) -> f64 {
+    let t = (julian_date(time) - 2_451_545.0) / 36_525.0; // Julian centuries since J2000
+    let seconds = -6.2e-6 * t.powi(3)
+        + 0.093_104 * t.powi(2)
+        + (876_600.0 * 3600.0 + 8_640_184.812_866) * t
+        + 67_310.548_41;
+    (seconds * TAU / 86_400.0).rem_euclid(TAU)
+}
+
+/// Rotation from ECEF to the scene frame at `time`.
+/// The scene frame is treated as TEME, so polar motion is ignored.
+pub fn earth_rotation(time: DateTime<Utc>) -> DQuat {
+    DQuat::from_rotation_z(gmst(time))
+}
+
+pub fn eci_to_ecef(position: Vec3, time: DateTime<Utc>) -> DVec3 {
+    earth_rotation(time).inverse() * position.as_dvec3()
+}
+
+/// ECEF velocity of something moving at `velocity` at `position` in the scene frame.
+pub fn eci_to_ecef_velocity(position: Vec3, velocity: Vec3, time: DateTime<Utc>) -> DVec3 {
+    let rotation = DVec3::Z * EARTH_ROTATION_RATE;
+    let relative = velocity.as_dvec3() - rotation.cross(position.as_dvec3());
+    earth_rotation(time).inverse() * relative
+}
+
+/// ECEF position of a satellite (or anything in the scene) at the current clock time.
+pub fn transform_to_ecef(transform: &Transform, clock: &SimulationClock) -> DVec3 {
+    eci_to_ecef(transform.translation, clock.now())
+}
+
+/// Geodetic latitude, longitude and altitude at the current clock time.
+pub fn transform_to_geodetic(transform: &Transform, clock: &SimulationClock) -> Geodetic {
+    ecef_to_geodetic(transform_to_ecef(transform, clock))
+}
+
+pub fn geodetic_to_ecef(geodetic: &Geodetic) -> DVec3 {
+    let (sin_lat, cos_lat) = geodetic.latitude.sin_cos();
+    let (sin_lon, cos_lon) = geodetic.longitude.sin_cos();
+    // radius of curvature in the prime vertical
+    let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+    DVec3::new(
+        (n + geodetic.altitude) * cos_lat * cos_lon,
+        (n + geodetic.altitude) * cos_lat * sin_lon,
+        (n * (1.0 - WGS84_E2) + geodetic.altitude) * sin_lat,
+    )
+}
+
+pub fn ecef_to_geodetic(position: DVec3) -> Geodetic {
+    let p = position.x.hypot(position.y);
+    let longitude = position.y.atan2(position.x);
+
+    let mut latitude = position.z.atan2(p * (1.0 - WGS84_E2));
+    for _ in 0..5 {
+        let sin_lat = latitude.sin();
+        let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+        latitude = (position.z + WGS84_E2 * n * sin_lat).atan2(p);
+    }
+    // stable at the poles, unlike p / cos(lat) - N
+    let (sin_lat, cos_lat) = latitude.sin_cos();
+    let altitude =
+        p * cos_lat + position.z * sin_lat - WGS84_A * (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+
+    Geodetic {
+        latitude,
+        longitude,
+        altitude,
+    }
+}
+
+fn rotate_earth(clock: Res<SimulationClock>, mut earth: Query<&mut Transform, With<Earth>>) {
+    let rotation = earth_rotation(clock.now()).as_quat();
+    for mut transform in &mut earth {
+        transform.rotation = rotation;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gmst() {
+        // Vallado, example 3-5: 1992-08-20 12:14 UT1, GMST = 152.578787886 deg
+        let time = parse_time_from_str("1992-08-20T12:14:00").unwrap();
+        assert!((gmst(time).to_degrees() - 152.578_787_886).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_geodetic_round_trip() {
+        let geodetic = Geodetic {
+            latitude: 39.9_f64.to_radians(),
+            longitude: 116.4_f64.to_radians(),
+            altitude: 0.05,
+        };
+        let result = ecef_to_geodetic(geodetic_to_ecef(&geodetic));
+        assert!((result.latitude - geodetic.latitude).abs() < 1e-9);
+        assert!((result.longitude - geodetic.longitude).abs() < 1e-9);
+        assert!((result.altitude - geodetic.altitude).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::math::DVec3;
+
+use super::*;
+
+const ASTRONOMICAL_UNIT: f64 = 149_597_870.7; // 天文单位(km)
+
+pub struct EphemerisPlugin;
+
+impl Plugin for EphemerisPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ephemeris>()
+            .add_systems(FixedPreUpdate, update_ephemeris);
+        if !is_headless(app) {
+            app.add_systems(Update, point_sunlight);
+        }
+    }
+}
+
+/// Positions of the Sun and the Moon in the scene frame (ECI) at the current clock time.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Ephemeris {
+    pub sun: DVec3,  // 太阳位置(km)
+    pub moon: DVec3, // 月球位置(km)
+}
+
+impl FromWorld for Ephemeris {
+    fn from_world(world: &mut World) -> Self {
+        Self::new(world.resource::<SimulationClock>().now())
+    }
+}
+
+impl Ephemeris {
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self {
+            sun: sun_position(time),
+            moon: moon_position(time),
+        }
+    }
+
+    /// Unit vector from the Earth to the Sun.
+    pub fn sun_direction(&self) -> DVec3 {
+        self.sun.normalize()
+    }
+
+    /// Whether a position is in the shadow of the Earth.
+    pub fn in_shadow(&self, position: DVec3) -> bool {
+        in_shadow(position, self.sun)
+    }
+
+    /// Beta angle of an orbit: the angle between the Sun and the orbital plane, in rad.
+    /// Positive when the Sun is on the side of the orbit normal.
+    pub fn beta_angle(&self, orbit: &Orbit) -> f64 {
+        let (sin_raan, cos_raan) = (orbit.longitude_of_ascending_node as f64).sin_cos();
+        let (sin_inc, cos_inc) = (orbit.inclination as f64).sin_cos();
+        let normal = DVec3::new(sin_raan * sin_inc, -cos_raan * sin_inc, cos_inc);
+        normal.dot(self.sun_direction()).clamp(-1.0, 1.0).asin()
+    }
+}
+
+/// The directional light of the Sun, shining on the Earth.
+#[derive(Component)]
+#[require(DirectionalLight)]
+pub struct Sunlight;
+
+/// Position of the Sun in the scene frame (ECI) at `time`, in km.
+/// Low-precision formula of the Astronomical Almanac, accurate to about 0.01° in 1950-2050.
+pub fn sun_position(time: DateTime<Utc>) -> DVec3 {
+    let n = julian_date(time) - 2_451_545.0; // days since J2000
+    let mean_longitude = (280.460 + 0.985_647_4 * n).to_radians();
+    let mean_anomaly = (357.528 + 0.985_600_3 * n).to_radians();
+    let ecliptic_longitude = mean_longitude
+        + (1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+    let distance = 1.000_14 - 0.016_71 * mean_anomaly.cos() - 0.000_14 * (2.0 * mean_anomaly).cos();
+
+    let (sin_lon, cos_lon) = ecliptic_longitude.sin_cos();
+    DVec3::new(
+        cos_lon,
+        obliquity.cos() * sin_lon,
+        obliquity.sin() * sin_lon,
+    ) * distance
+        * ASTRONOMICAL_UNIT
+}
+
+/// Position of the Moon in the scene frame (ECI) at `time`, in km.
+/// Low-precision formula of the Astronomical Almanac, accurate to about 0.3° in 1950-2050.
+pub fn moon_position(time: DateTime<Utc>) -> DVec3 {
+    let t = (julian_date(time) - 2_451_545.0) / 36_525.0; // Julian centuries since J2000
+    let sin = |a: f64, b: f64| (a + b * t).to_radians().sin();
+    let cos = |a: f64, b: f64| (a + b * t).to_radians().cos();
+
+    let longitude = (218.32 + 481_267.881 * t + 6.29 * sin(135.0, 477_198.87)
+        - 1.27 * sin(259.3, -413_335.36)
+        + 0.66 * sin(235.7, 890_534.22)
+        + 0.21 * sin(269.9, 954_397.74)
+        - 0.19 * sin(357.5, 35_999.05)
+        - 0.11 * sin(186.5, 966_404.03))
+    .to_radians();
+    let latitude = (5.13 * sin(93.3, 483_202.02) + 0.28 * sin(228.2, 960_400.89)
+        - 0.28 * sin(318.3, 6_003.15)
+        - 0.17 * sin(217.6, -407_332.21))
+    .to_radians();
+    let parallax = (0.9508
+        + 0.0518 * cos(135.0, 477_198.87)
+        + 0.0095 * cos(259.3, -413_335.36)
+        + 0.0078 * cos(235.7, 890_534.22)
+        + 0.0028 * cos(269.9, 954_397.74))
+    .to_radians();
+    let obliquity = (23.439_291 - 0.013_004_2 * t).to_radians();
+    let distance = 6378.137 / parallax.sin();
+
+    let (sin_lon, cos_lon) = longitude.sin_cos();
+    let (sin_lat, cos_lat) = latitude.sin_cos();
+    let (sin_obl, cos_obl) = obliquity.sin_cos();
+    DVec3::new(
+        cos_lat * cos_lon,
+        cos_obl * cos_lat * sin_lon - sin_obl * sin_lat,
+        sin_obl * cos_lat * sin_lon + cos_obl * sin_lat,
+    ) * distance
+}
+
+/// Whether a position is in the shadow of the Earth, a cylinder behind the Earth.
+pub fn in_shadow(position: DVec3, sun: DVec3) -> bool {
+    let direction = sun.normalize();
+    let along = position.dot(direction);
+    along < 0.0 && (position - along * direction).length() < EARTH_RADIUS as f64
+}
+
+fn update_ephemeris(clock: Res<SimulationClock>, mut ephemeris: ResMut<Ephemeris>) {
+    *ephemeris = Ephemeris::new(clock.now());
+}
+
+fn point_sunlight(ephemeris: Res<Ephemeris>, mut sunlight: Query<&mut Transform, With<Sunlight>>) {
+    let direction = -ephemeris.sun_direction().as_vec3();
+    for mut transform in &mut sunlight {
+        transform.look_to(direction, Vec3::Z);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sun_position() {
+        // March equinox of 2024
+        let time = parse_time_from_str("2024-03-20T03:06:00").unwrap();
+        let sun = sun_position(time) / ASTRONOMICAL_UNIT;
+        assert!((sun.length() - 0.996).abs() < 1e-3);
+        assert!(sun.x > 0.99);
+        assert!(sun.z.abs() < 1e-3);
+
+        assert!(in_shadow(DVec3::new(-7000.0, 0.0, 0.0), sun));
+        assert!(!in_shadow(DVec3::new(0.0, 7000.0, 0.0), sun));
+        assert!(!in_shadow(DVec3::new(7000.0, 0.0, 0.0), sun));
+    }
+
+    #[test]
+    fn test_moon_position() {
+        // Vallado, Fundamentals of Astrodynamics and Applications, example 5-3
+        let time = parse_time_from_str("1994-04-28T00:00:00").unwrap();
+        let moon = moon_position(time);
+        let expected = DVec3::new(-134_240.626, -311_571.590, -126_693.785);
+        assert!(moon.angle_between(expected).to_degrees() < 0.5);
+        assert!((moon.length() / expected.length() - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_beta_angle() {
+        let orbit = |inclination: f32, raan: f32| {
+            Orbit::from_slice(&[
+                0.0011,
+                0.0,
+                inclination.to_radians(),
+                raan.to_radians(),
+                0.0,
+            ])
+            .unwrap()
+        };
+        // March equinox of 2024, with the Sun along +x
+        let ephemeris = Ephemeris::new(parse_time_from_str("2024-03-20T03:06:00").unwrap());
+        let beta = |orbit: &Orbit| ephemeris.beta_angle(orbit).to_degrees();
+
+        // a dawn-dusk sun-synchronous orbit nearly faces the Sun: RAAN 90° puts the normal at
+        // (sin i, 0, cos i), so beta = 180° - i
+        assert!((beta(&orbit(97.6, 90.0)) - 82.4).abs() < 0.1);
+        assert!((beta(&orbit(97.6, 270.0)) + 82.4).abs() < 0.1);
+        // a noon-midnight orbit has the Sun in its plane
+        assert!(beta(&orbit(97.6, 0.0)).abs() < 0.1);
+
+        // an equatorial orbit at the June solstice sees the Sun at the obliquity
+        let ephemeris = Ephemeris::new(parse_time_from_str("2024-06-20T20:51:00").unwrap());
+        assert!((ephemeris.beta_angle(&orbit(0.0, 0.0)).to_degrees() - 23.44).abs() < 0.05);
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::{ecs::relationship::Relationship, math::DVec3};
+use chrono::TimeDelta;
+
+use super::{link_log::*, *};
+
+const VISIBILITY_STEP: i64 = 15; // 预测可见时长的步长(s)
+const VISIBILITY_HORIZON: i64 = 30 * 60; // 预测可见时长的上限(s)
+
+pub struct GroundStationPlugin;
+
+impl Plugin for GroundStationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(FixedUpdate, update_ground_station_position)
+            // after all satellites are moved
+            .add_systems(FixedPostUpdate, update_ground_links);
+    }
+}
+
+/// A ground station fixed on the Earth.
+#[derive(Component, Debug)]
+#[require(Transform, GroundLinks)]
+pub struct GroundStation {
+    ecef: DVec3,        // 地固系位置(km)
+    zenith: DVec3,      // 地固系天顶方向
+    min_elevation: f64, // 最小仰角(rad)
+    policy: LinkPolicy,
+    max_links: usize,
+}
+
+/// Satellites linked to a ground station.
+#[derive(Component, Default)]
+pub struct GroundLinks {
+    pub(super) satellites: Vec<Entity>,
+}
+
+impl GroundStation {
+    pub fn new(config: &GroundStationConfig) -> Self {
+        let geodetic = Geodetic {
+            latitude: config.latitude.to_radians(),
+            longitude: config.longitude.to_radians(),
+            altitude: config.altitude,
+        };
+        let (sin_lat, cos_lat) = geodetic.latitude.sin_cos();
+        let (sin_lon, cos_lon) = geodetic.longitude.sin_cos();
+        Self {
+            ecef: geodetic_to_ecef(&geodetic),
+            zenith: DVec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat),
+            min_elevation: config.min_elevation.to_radians(),
+            policy: config.policy,
+            max_links: config.max_links,
+        }
+    }
+
+    /// Position and zenith in the scene frame at `time`.
+    fn position_and_zenith(&self, time: DateTime<Utc>) -> (DVec3, DVec3) {
+        let rotation = earth_rotation(time);
+        (rotation * self.ecef, rotation * self.zenith)
+    }
+
+    /// Remaining time (s) until the satellite goes below the elevation mask, by two-body motion.
+    /// Capped at `VISIBILITY_HORIZON`.
+    fn remaining_visibility(&self, time: DateTime<Utc>, orbit: &Orbit, mean_anomaly: f32) -> i64 {
+        (VISIBILITY_STEP..=VISIBILITY_HORIZON)
+            .step_by(VISIBILITY_STEP as usize)
+            .find(|&dt| {
+                let (position, zenith) = self.position_and_zenith(time + TimeDelta::seconds(dt));
+                let satellite =
+                    get_pos_from_elements(orbit, mean_anomaly + orbit.mean_motion * dt as f32);
+                elevation(position, zenith, satellite) < self.min_elevation
+            })
+            .unwrap_or(VISIBILITY_HORIZON)
+    }
+}
+
+/// Elevation (rad) of a target seen from a position with the given zenith.
+fn elevation(position: DVec3, zenith: DVec3, target: Vec3) -> f64 {
+    let direction = target.as_dvec3() - position;
+    (direction.dot(zenith) / direction.length()).asin()
+}
+
+fn setup(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Cuboid::from_length(60.));
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.2, 1.0, 0.4),
+        unlit: true,
+        ..default()
+    });
+
+    for station in &config.ground_stations {
+        info!("Add ground station: {}", station.name);
+        commands.spawn((
+            GroundStation::new(station),
+            Name::new(station.name.clone()),
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+        ));
+    }
+}
+
+/// Rotate ground stations with the Earth.
+fn update_ground_station_position(
+    clock: Res<SimulationClock>,
+    mut stations: Query<(&GroundStation, &mut Transform)>,
+) {
+    let rotation = earth_rotation(clock.now());
+    for (station, mut transform) in &mut stations {
+        transform.translation = (rotation * station.ecef).as_vec3();
+        transform.rotation = rotation.as_quat();
+    }
+}
+
+/// Drop links to satellites below the elevation mask, then link new satellites by the policy.
+pub(super) fn update_ground_links(
+    clock: Res<SimulationClock>,
+    mut stations: Query<(Entity, &GroundStation, &mut GroundLinks)>,
+    satellites: Query<(Entity, &Transform, &Satellite, &Following), Without<LowPower>>,
+    low_power: Query<(), With<LowPower>>,
+    orbits: Query<&Orbit>,
+    mut link_events: LinkEvents,
+) {
+    let now = clock.now();
+    for (entity, station, mut links) in &mut stations {
+        let (position, zenith) = station.position_and_zenith(now);
+        let visible = |transform: &Transform| {
+            elevation(position, zenith, transform.translation) >= station.min_elevation
+        };
+
+        links.satellites.retain(|&sat| {
+            let reason = match satellites.get(sat) {
+                Ok((_, transform, ..)) if visible(transform) => return true,
+                Ok(_) => LinkDownReason::Occluded,
+                Err(_) if low_power.contains(sat) => LinkDownReason::LowPower,
+                Err(_) => LinkDownReason::Manual,
+            };
+            link_events.down(entity, sat, reason);
+            false
+        });
+        if links.satellites.len() >= station.max_links {
+            continue;
+        }
+
+        let mut candidates: Vec<(Entity, f64)> = satellites
+            .iter()
+            .filter(|(sat, transform, ..)| !links.satellites.contains(sat) && visible(transform))
+            .filter_map(|(sat, transform, satellite, following)| {
+                let score = match station.policy {
+                    LinkPolicy::HighestElevation => {
+                        elevation(position, zenith, transform.translation)
+                    }
+                    LinkPolicy::Nearest => -transform.translation.as_dvec3().distance(position),
+                    LinkPolicy::LongestVisibility => {
+                        let orbit = orbits.get(following.get()).ok()?;
+                        station.remaining_visibility(now, orbit, satellite.mean_anomaly) as f64
+                    }
+                };
+                Some((sat, score))
+            })
+            .collect();
+        // best first
+        candidates.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+
+        let count = station.max_links - links.satellites.len();
+        for &(sat, _) in candidates.iter().take(count) {
+            links.satellites.push(sat);
+            link_events.up(entity, sat, LinkKind::Ground);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_elevation() {
+        let station = GroundStation::new(&GroundStationConfig {
+            name: "Equator".to_string(),
+            latitude: 0.0,
+            longitude: 0.0,
+            altitude: 0.0,
+            min_elevation: 10.0,
+            policy: LinkPolicy::HighestElevation,
+            max_links: 1,
+        });
+        let overhead = Vec3::new(7000.0, 0.0, 0.0);
+        let horizon = Vec3::new(6378.137, 2000.0, 0.0);
+        let elevation_of = |target| elevation(station.ecef, station.zenith, target).to_degrees();
+        assert!((elevation_of(overhead) - 90.0).abs() < 1e-3);
+        assert!(elevation_of(horizon).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::ecs::entity::EntityHashMap;
+use serde::Serialize;
+
+use super::*;
+
+const BOLTZMANN: f64 = -228.6; // 玻尔兹曼常数(dBW/K/Hz)
+const MIN_DISTANCE: f64 = 1e-3; // 计算路径损耗的最小距离(km)
+
+/// The budget of one link, from the current distance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LinkBudget {
+    pub distance: f64,  // 距离(km)
+    pub path_loss: f64, // 自由空间路径损耗(dB)
+    pub snr: f64,       // 信噪比(dB)
+    pub capacity: f64,  // 香农容量(bit/s)
+    pub delay: f64,     // 单程传播时延(s)
+}
+
+impl LinkBudget {
+    pub fn new(config: &LinkBudgetConfig, distance: f64) -> Self {
+        // FSPL = 20 log10(d[km]) + 20 log10(f[GHz]) + 92.45
+        // clamped so that coincident ends don't get an infinite SNR
+        let path_loss =
+            20.0 * distance.max(MIN_DISTANCE).log10() + 20.0 * config.frequency.log10() + 92.45;
+        let received_power = config.tx_power + config.tx_gain + config.rx_gain - path_loss;
+        let bandwidth = config.bandwidth * 1e6; // MHz to Hz
+        let noise_power =
+            BOLTZMANN + 10.0 * config.noise_temperature.log10() + 10.0 * bandwidth.log10();
+        let snr = received_power - noise_power;
+        Self {
+            distance,
+            path_loss,
+            snr,
+            capacity: bandwidth * (1.0 + 10f64.powf(snr / 10.0)).log2(),
+            delay: propagation_delay(distance),
+        }
+    }
+}
+
+/// Budgets of all links in `Connections`, by the other end of the link.
+#[derive(Component, Default, Debug)]
+pub struct LinkBudgets(pub EntityHashMap<LinkBudget>);
+
+/// Compute the budget of every link from the current positions.
+pub(super) fn update_link_budgets(
+    config: Res<Config>,
+    mut satellites: Query<(&Connections, &Transform, &mut LinkBudgets)>,
+    positions: Query<&Transform, With<Satellite>>,
+) {
+    for (conns, transform, mut budgets) in &mut satellites {
+        budgets.0.clear();
+        for &other in &conns.connections {
+            let Ok(other_transform) = positions.get(other) else {
+                continue;
+            };
+            let distance = transform.translation.distance(other_transform.translation) as f64;
+            budgets
+                .0
+                .insert(other, LinkBudget::new(&config.link_budget, distance));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_link_budget() {
+        let budget = LinkBudget::new(&LinkBudgetConfig::default(), 1000.0);
+        // 20 log10(1000) + 20 log10(26) + 92.45
+        assert!((budget.path_loss - 180.75).abs() < 0.01);
+        // 10 + 38 + 38 - 180.75 + 228.6 - 10 log10(500) - 10 log10(5e8)
+        assert!((budget.snr - 19.87).abs() < 0.01);
+        assert!(budget.capacity > 3e9 && budget.capacity < 4e9);
+    }
+
+    #[test]
+    fn test_zero_distance() {
+        let budget = LinkBudget::new(&LinkBudgetConfig::default(), 0.0);
+        assert_eq!(budget.distance, 0.0);
+        assert_eq!(budget.delay, 0.0);
+        assert!(budget.path_loss.is_finite());
+        assert!(budget.snr.is_finite());
+        assert!(budget.capacity.is_finite());
+        assert_eq!(
+            budget,
+            LinkBudget {
+                distance: 0.0,
+                delay: 0.0,
+                ..LinkBudget::new(&LinkBudgetConfig::default(), MIN_DISTANCE)
+            }
+        );
+    }
+}
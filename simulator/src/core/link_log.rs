@@ -0,0 +1,296 @@
+use bevy::{ecs::system::SystemParam, platform::collections::HashMap};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use super::*;
+
+/// Number of recent events kept for the network interface.
+const MAX_RECENT_EVENTS: usize = 10_000;
+
+pub struct LinkLogPlugin;
+
+impl Plugin for LinkLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LinkEvent>()
+            .init_resource::<LinkLog>()
+            .add_systems(FixedPreUpdate, reset_on_jump);
+    }
+}
+
+/// An inter-satellite link or a ground link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Isl,
+    Ground,
+}
+
+/// Why a link was torn down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkDownReason {
+    /// Beyond `connection_distance`.
+    OutOfRange,
+    /// Blocked by the Earth, or below the elevation mask of a ground station.
+    Occluded,
+    /// Replaced by a better link, as a satellite has a limited number of terminals.
+    Capacity,
+    /// Shut down in the polar region of the `plus_grid` topology.
+    PolarRegion,
+    /// A satellite ran low on power.
+    LowPower,
+    /// Disconnected on request, or a node was removed.
+    Manual,
+}
+
+/// A link established or torn down.
+#[derive(Event, Debug, Clone, Serialize)]
+pub struct LinkEvent {
+    pub time: f64, // 仿真时间(s)
+    /// IDs of the two ends, in no particular order: satellite IDs or ground station names.
+    pub a: String,
+    pub b: String,
+    pub kind: LinkKind,
+    /// `None` when the link is established.
+    pub reason: Option<LinkDownReason>,
+    /// How long the link was up, when torn down.
+    pub lifetime: Option<f64>, // (s)
+}
+
+impl LinkEvent {
+    pub fn is_up(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// Churn statistics since the start, or since the last clock jump.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStats {
+    pub active_isls: usize,
+    pub active_ground_links: usize,
+    pub established: u64,
+    pub torn_down: u64,
+    pub mean_duration: Option<f64>,  // 已断开链路的平均持续时间(s)
+    pub established_per_second: f64, // 每秒建立的链路数
+    pub torn_down_per_second: f64,   // 每秒断开的链路数
+    pub reasons: HashMap<LinkDownReason, u64>,
+    /// Number of satellites by their number of ISLs.
+    pub degree_histogram: Vec<usize>,
+}
+
+/// A link that is up.
+struct ActiveLink {
+    established: f64, // 建立时间(s)
+    kind: LinkKind,
+    ids: [String; 2], // 两端的ID, 在节点移除后仍可记录断开事件
+}
+
+/// Active links with the time they were established, recent events and churn counters.
+#[derive(Resource, Default)]
+pub struct LinkLog {
+    links: HashMap<(Entity, Entity), ActiveLink>,
+    recent: VecDeque<LinkEvent>,
+    since: f64, // 统计开始的仿真时间(s)
+    jumps: u32,
+    established: u64,
+    torn_down: u64,
+    total_lifetime: f64, // 已断开链路的总持续时间(s)
+    reasons: HashMap<LinkDownReason, u64>,
+}
+
+/// The same key for both directions of a link.
+fn pair(a: Entity, b: Entity) -> (Entity, Entity) {
+    (a.min(b), a.max(b))
+}
+
+impl LinkLog {
+    fn up(
+        &mut self,
+        now: f64,
+        [a, b]: [Entity; 2],
+        kind: LinkKind,
+        ids: [String; 2],
+    ) -> Option<LinkEvent> {
+        if self.links.contains_key(&pair(a, b)) {
+            return None;
+        }
+        let [a_id, b_id] = ids.clone();
+        self.links.insert(
+            pair(a, b),
+            ActiveLink {
+                established: now,
+                kind,
+                ids,
+            },
+        );
+        self.established += 1;
+        Some(self.push(LinkEvent {
+            time: now,
+            a: a_id,
+            b: b_id,
+            kind,
+            reason: None,
+            lifetime: None,
+        }))
+    }
+
+    fn down(&mut self, now: f64, [a, b]: [Entity; 2], reason: LinkDownReason) -> Option<LinkEvent> {
+        let ActiveLink {
+            established,
+            kind,
+            ids: [a_id, b_id],
+        } = self.links.remove(&pair(a, b))?;
+        let lifetime = (now - established).max(0.0);
+        self.torn_down += 1;
+        self.total_lifetime += lifetime;
+        *self.reasons.entry(reason).or_default() += 1;
+        Some(self.push(LinkEvent {
+            time: now,
+            a: a_id,
+            b: b_id,
+            kind,
+            reason: Some(reason),
+            lifetime: Some(lifetime),
+        }))
+    }
+
+    fn push(&mut self, event: LinkEvent) -> LinkEvent {
+        if self.recent.len() == MAX_RECENT_EVENTS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(event.clone());
+        event
+    }
+
+    /// Recent events, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &LinkEvent> {
+        self.recent.iter()
+    }
+
+    /// Statistics at `now`. The degree histogram counts the ISLs of every satellite.
+    pub fn stats(&self, now: f64, satellites: impl Iterator<Item = Entity>) -> LinkStats {
+        let mut degrees: HashMap<Entity, usize> = satellites.map(|sate| (sate, 0)).collect();
+        for (a, b) in self.isls() {
+            for sate in [a, b] {
+                if let Some(degree) = degrees.get_mut(&sate) {
+                    *degree += 1;
+                }
+            }
+        }
+        let mut degree_histogram = vec![];
+        for degree in degrees.into_values() {
+            if degree_histogram.len() <= degree {
+                degree_histogram.resize(degree + 1, 0);
+            }
+            degree_histogram[degree] += 1;
+        }
+        let elapsed = now - self.since;
+        let per_second = |count: u64| {
+            if elapsed > 0.0 {
+                count as f64 / elapsed
+            } else {
+                0.0
+            }
+        };
+        let active_isls = self.isls().count();
+        LinkStats {
+            active_isls,
+            active_ground_links: self.links.len() - active_isls,
+            established: self.established,
+            torn_down: self.torn_down,
+            mean_duration: (self.torn_down > 0)
+                .then(|| self.total_lifetime / self.torn_down as f64),
+            established_per_second: per_second(self.established),
+            torn_down_per_second: per_second(self.torn_down),
+            reasons: self.reasons.clone(),
+            degree_histogram,
+        }
+    }
+
+    fn isls(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.links
+            .iter()
+            .filter(|(_, link)| link.kind == LinkKind::Isl)
+            .map(|(pair, _)| *pair)
+    }
+
+    /// Restart the statistics from `now`. Lifetimes of active links are counted from `now`.
+    fn reset(&mut self, now: f64) {
+        for link in self.links.values_mut() {
+            link.established = now;
+        }
+        self.recent.clear();
+        self.since = now;
+        self.established = 0;
+        self.torn_down = 0;
+        self.total_lifetime = 0.0;
+        self.reasons.clear();
+    }
+}
+
+/// Records links established and torn down, and emits them as `LinkEvent`s.
+#[derive(SystemParam)]
+pub(super) struct LinkEvents<'w, 's> {
+    clock: Res<'w, SimulationClock>,
+    log: ResMut<'w, LinkLog>,
+    events: EventWriter<'w, LinkEvent>,
+    names: Query<'w, 's, &'static Name>,
+}
+
+impl LinkEvents<'_, '_> {
+    pub(super) fn up(&mut self, a: Entity, b: Entity, kind: LinkKind) {
+        let ids = [a, b].map(|entity| {
+            self.names
+                .get(entity)
+                .map_or_else(|_| entity.to_string(), |name| name.to_string())
+        });
+        if let Some(event) = self.log.up(self.clock.elapsed_secs(), [a, b], kind, ids) {
+            self.events.write(event);
+        }
+    }
+
+    pub(super) fn down(&mut self, a: Entity, b: Entity, reason: LinkDownReason) {
+        if let Some(event) = self.log.down(self.clock.elapsed_secs(), [a, b], reason) {
+            self.events.write(event);
+        }
+    }
+}
+
+fn reset_on_jump(clock: Res<SimulationClock>, mut log: ResMut<LinkLog>) {
+    let now = clock.elapsed_secs();
+    if clock.jumps() != log.jumps {
+        log.jumps = clock.jumps();
+        log.reset(now);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_link_log() {
+        let [a, b, c] = [1, 2, 3].map(Entity::from_raw);
+        let ids = |a: &str, b: &str| [a.to_string(), b.to_string()];
+        let mut log = LinkLog::default();
+        assert!(log.up(0.0, [a, b], LinkKind::Isl, ids("a", "b")).is_some());
+        assert!(log.up(0.0, [b, a], LinkKind::Isl, ids("b", "a")).is_none());
+        log.up(5.0, [a, c], LinkKind::Ground, ids("a", "c"));
+
+        let event = log.down(10.0, [b, a], LinkDownReason::OutOfRange).unwrap();
+        assert_eq!(event.lifetime, Some(10.0));
+        assert_eq!((event.a.as_str(), event.b.as_str()), ("a", "b"));
+        assert!(log.down(10.0, [a, b], LinkDownReason::OutOfRange).is_none());
+        log.down(20.0, [c, a], LinkDownReason::Occluded);
+
+        log.up(20.0, [a, b], LinkKind::Isl, ids("a", "b"));
+        let stats = log.stats(20.0, [a, b, c].into_iter());
+        assert_eq!((stats.established, stats.torn_down), (3, 2));
+        assert_eq!(stats.mean_duration, Some(12.5));
+        assert_eq!(stats.torn_down_per_second, 0.1);
+        assert_eq!(stats.reasons[&LinkDownReason::Occluded], 1);
+        assert_eq!((stats.active_isls, stats.active_ground_links), (1, 0));
+        assert_eq!(stats.degree_histogram, vec![1, 2]);
+        assert_eq!(log.recent().filter(|event| event.is_up()).count(), 3);
+    }
+}
@@ -1,4 +1,6 @@
-use super::*;
+use bevy::ecs::{entity::EntityHashSet, relationship::Relationship};
+
+use super::{link_log::*, *};
 
 pub struct ManagerPlugin;
 
@@ -9,7 +11,15 @@ impl Plugin for ManagerPlugin {
         app.add_event::<SpawnSatellites>()
             .add_event::<SpawnOrbits>()
             .add_event::<AttachSatellites>()
-            .add_systems(FixedUpdate, (receive_events, spawn).chain());
+            .add_event::<RemoveSatellites>()
+            .add_event::<RemoveOrbits>()
+            // before links are updated, so nothing refers to removed satellites
+            .add_systems(
+                FixedUpdate,
+                (receive_events, spawn, remove)
+                    .chain()
+                    .before(rebuild_spatial_index),
+            );
     }
 }
 
@@ -19,7 +29,7 @@ pub struct SatelliteManager {
     material: Handle<StandardMaterial>,
     orbit_entities: Vec<Entity>,
     unspawned_sats: Vec<(String, OrbitalElements)>,
-    unspawned_orbs: Vec<Orbit>,
+    unspawned_orbs: Vec<(Entity, Orbit)>,
     unattached_sats: Vec<(Entity, String, Satellite)>,
 }
 
@@ -29,6 +39,10 @@ impl SatelliteManager {
         self.material = material;
     }
 
+    pub(super) fn material(&self) -> &Handle<StandardMaterial> {
+        &self.material
+    }
+
     pub fn add_satellites(&mut self, satellites: Vec<(String, OrbitalElements)>) {
         self.unspawned_sats.extend(satellites);
     }
@@ -39,16 +53,31 @@ pub struct SpawnSatellites {
     pub satellites: Vec<(String, OrbitalElements)>,
 }
 
+/// Insert orbits into entities reserved by the sender, e.g. with `Commands::spawn_empty`,
+/// so the sender knows their IDs before they are spawned.
 #[derive(Event)]
 pub struct SpawnOrbits {
-    pub orbits: Vec<Orbit>,
+    pub orbits: Vec<(Entity, Orbit)>,
 }
 
+/// Spawn satellites on existing orbits, or on orbits spawned by `SpawnOrbits` in the same step.
 #[derive(Event)]
 pub struct AttachSatellites {
     pub satellites: Vec<(Entity, String, Satellite)>,
 }
 
+/// Despawn satellites and drop their links. Orbits left without satellites are despawned too.
+#[derive(Event)]
+pub struct RemoveSatellites {
+    pub satellites: Vec<Entity>,
+}
+
+/// Despawn orbits with all their satellites.
+#[derive(Event)]
+pub struct RemoveOrbits {
+    pub orbits: Vec<Entity>,
+}
+
 fn receive_events(
     mut spawn_sat_events: EventReader<SpawnSatellites>,
     mut spawn_orbit_events: EventReader<SpawnOrbits>,
@@ -87,9 +116,9 @@ fn spawn(mut commands: Commands, mut satellite_spawner: ResMut<SatelliteManager>
     let iter: Vec<_> = satellite_spawner
         .unspawned_orbs
         .drain(..)
-        .map(|orbit| {
-            commands.spawn(orbit).id()
-            // satellite_spawner.orbit_entities.push(orbit_entity);
+        .map(|(orbit_entity, orbit)| {
+            commands.entity(orbit_entity).insert(orbit);
+            orbit_entity
         })
         .collect();
     if iter.len() > 0 {
@@ -103,15 +132,18 @@ fn spawn(mut commands: Commands, mut satellite_spawner: ResMut<SatelliteManager>
         .drain(..)
         .map(|elements| {
             let (satellite_id, satellite) = elements;
-            let (orbit, mean_anomaly) = satellite.sep_out_mean_anomaly();
+            let (orbit, mean_anomaly, sgp4) = satellite.sep_out_mean_anomaly();
             let orbit_entity = commands.spawn(orbit).id();
-            commands.spawn(create_satellite(
+            let mut satellite = commands.spawn(create_satellite(
                 satellite_id,
                 orbit_entity,
                 mean_anomaly,
                 mesh.clone(),
                 material.clone(),
             ));
+            if let Some(sgp4) = sgp4 {
+                satellite.insert(sgp4);
+            }
             orbit_entity
         })
         .collect();
@@ -138,3 +170,240 @@ fn spawn(mut commands: Commands, mut satellite_spawner: ResMut<SatelliteManager>
         commands.trigger(OrbitChanged);
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+fn remove(
+    mut commands: Commands,
+    mut remove_sat_events: EventReader<RemoveSatellites>,
+    mut remove_orbit_events: EventReader<RemoveOrbits>,
+    mut attach_sat_events: EventReader<AttachSatellites>,
+    mut satellite_spawner: ResMut<SatelliteManager>,
+    orbits: Query<Option<&FollowedBy>, With<Orbit>>,
+    satellites: Query<&Following>,
+    mut connections: Query<&mut Connections>,
+    mut link_events: LinkEvents,
+) {
+    // orbits that satellites are being attached to in this step are kept
+    let attaching: EntityHashSet = satellite_spawner
+        .unattached_sats
+        .iter()
+        .map(|(orbit, _, _)| *orbit)
+        .chain(
+            attach_sat_events
+                .read()
+                .flat_map(|event| event.satellites.iter().map(|(orbit, _, _)| *orbit)),
+        )
+        .collect();
+    let mut removed_orbits: EntityHashSet = remove_orbit_events
+        .read()
+        .flat_map(|event| event.orbits.iter().copied())
+        .filter(|orbit| orbits.contains(*orbit))
+        .filter(|orbit| {
+            let attached = attaching.contains(orbit);
+            if attached {
+                warn!("Keep orbit {}, satellites are being attached to it", orbit);
+            }
+            !attached
+        })
+        .collect();
+    // satellites of the removed orbits go with them
+    let mut removed_sats: EntityHashSet = removed_orbits
+        .iter()
+        .filter_map(|orbit| orbits.get(*orbit).ok().flatten())
+        .flat_map(|sates| sates.iter())
+        .collect();
+    removed_sats.extend(
+        remove_sat_events
+            .read()
+            .flat_map(|event| event.satellites.iter().copied())
+            .filter(|sate| satellites.contains(*sate)),
+    );
+    if removed_sats.is_empty() && removed_orbits.is_empty() {
+        return;
+    }
+
+    // drop every link to the removed satellites
+    for &sate in &removed_sats {
+        let Ok(mut conns) = connections.get_mut(sate) else {
+            continue;
+        };
+        let others = std::mem::take(&mut conns.connections);
+        for other in others {
+            if let Ok(mut other_conns) = connections.get_mut(other) {
+                other_conns.connections.retain(|&s| s != sate);
+            }
+            link_events.down(sate, other, LinkDownReason::Manual);
+        }
+    }
+
+    // orbits left without satellites
+    let emptied: EntityHashSet = removed_sats
+        .iter()
+        .filter_map(|sate| satellites.get(*sate).ok())
+        .map(|following| following.get())
+        .filter(|orbit| !attaching.contains(orbit))
+        .filter(|orbit| {
+            orbits.get(*orbit).is_ok_and(|sates| {
+                sates.is_none_or(|sates| sates.iter().all(|sate| removed_sats.contains(&sate)))
+            })
+        })
+        .collect();
+    removed_orbits.extend(emptied);
+
+    info!(
+        "Remove {} satellites and {} orbits",
+        removed_sats.len(),
+        removed_orbits.len()
+    );
+    for &sate in &removed_sats {
+        commands.entity(sate).despawn();
+    }
+    for &orbit in &removed_orbits {
+        commands.entity(orbit).despawn();
+    }
+    satellite_spawner
+        .orbit_entities
+        .retain(|orbit| !removed_orbits.contains(orbit));
+    commands.trigger(OrbitChanged);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test_app, *};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_remove() {
+        const CONFIG: &str = r#"
+            [Network]
+            port = 0
+            [Display]
+            orbit = false
+            connection = false
+            [Simulation]
+            start_time = "2025-05-01T00:00:00"
+            time_speed = 1.0
+            connection_distance = 5000.0
+            connection_number = 4
+            seed = 42
+        "#;
+        let semi_major_axis: f32 = EARTH_RADIUS + 550.0;
+        let mean_motion = (FACTOR.powi(3) / semi_major_axis.powi(3)).sqrt();
+        let elements = [mean_motion, 0.0, 53f32.to_radians(), 0.0, 0.0];
+        let own_orbit = |mean_anomaly: f32| {
+            let mut data = [mean_anomaly; 6];
+            data[..5].copy_from_slice(&elements);
+            OrbitalElements::from_slice(&data).unwrap()
+        };
+
+        let mut app = test_app(CONFIG, 1);
+        // the first update only starts the clocks
+        app.update();
+        // A and B share an orbit, C and the others are each on their own, a few hundred km apart
+        let world = app.world_mut();
+        let shared = world.spawn_empty().id();
+        world.send_event(SpawnOrbits {
+            orbits: vec![(shared, Orbit::from_slice(&elements).unwrap())],
+        });
+        world.send_event(AttachSatellites {
+            satellites: vec![
+                (shared, "A".to_string(), Satellite { mean_anomaly: 0.0 }),
+                (shared, "B".to_string(), Satellite { mean_anomaly: 0.1 }),
+            ],
+        });
+        world.send_event(SpawnSatellites {
+            satellites: (0..8)
+                .map(|i| (format!("S{}", i), own_orbit(0.05 + 0.1 * i as f32)))
+                .collect(),
+        });
+        for _ in 0..20 {
+            app.update();
+        }
+
+        let world = app.world_mut();
+        let entities: HashMap<String, Entity> = world
+            .query::<(Entity, &Name)>()
+            .iter(world)
+            .map(|(entity, name)| (name.to_string(), entity))
+            .collect();
+        let names: HashMap<Entity, String> = entities
+            .iter()
+            .map(|(name, entity)| (*entity, name.clone()))
+            .collect();
+        // a link by the IDs of its ends, in either order
+        let pair = |a: &str, b: &str| {
+            let mut ends = [a.to_string(), b.to_string()];
+            ends.sort();
+            ends
+        };
+        let (a, b, c) = (entities["A"], entities["B"], entities["S0"]);
+        let own = world.get::<Following>(c).unwrap().get();
+        let mut linked: Vec<[String; 2]> = ["B", "S0"]
+            .iter()
+            .flat_map(|id| {
+                let conns = &world.get::<Connections>(entities[*id]).unwrap().connections;
+                conns.iter().map(|other| pair(id, &names[other]))
+            })
+            .collect();
+        linked.sort();
+        linked.dedup();
+        assert!(!linked.is_empty());
+        assert_eq!(world.resource::<SatelliteManager>().orbit_entities.len(), 9);
+
+        // C leaves its orbit empty, while the shared orbit keeps A
+        world.send_event(RemoveSatellites {
+            satellites: vec![b, c],
+        });
+        app.update();
+
+        let world = app.world_mut();
+        assert!(world.get_entity(b).is_err() && world.get_entity(c).is_err());
+        for (name, conns) in world.query::<(&Name, &Connections)>().iter(world) {
+            assert!(
+                !conns
+                    .connections
+                    .iter()
+                    .any(|other| *other == b || *other == c),
+                "{} is still linked to a removed satellite",
+                name
+            );
+        }
+        let mut manual: Vec<[String; 2]> = world
+            .resource::<LinkLog>()
+            .recent()
+            .filter(|event| event.reason == Some(LinkDownReason::Manual))
+            .map(|event| pair(&event.a, &event.b))
+            .collect();
+        manual.sort();
+        assert_eq!(manual, linked);
+        assert!(world.get_entity(own).is_err());
+        assert!(world.get::<Orbit>(shared).is_some());
+        let orbit_entities = &world.resource::<SatelliteManager>().orbit_entities;
+        assert_eq!(orbit_entities.len(), 8);
+        assert!(orbit_entities.contains(&shared) && !orbit_entities.contains(&own));
+
+        // the last satellite of an orbit is removed as another one is attached to it
+        world.send_event(RemoveSatellites {
+            satellites: vec![a],
+        });
+        world.send_event(AttachSatellites {
+            satellites: vec![(shared, "D".to_string(), Satellite { mean_anomaly: PI })],
+        });
+        app.update();
+
+        let world = app.world_mut();
+        assert!(world.get_entity(a).is_err());
+        assert!(world.get::<Orbit>(shared).is_some());
+        let followers: Vec<String> = world
+            .query::<(&Name, &Following)>()
+            .iter(world)
+            .filter(|(_, following)| following.get() == shared)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        assert_eq!(followers, ["D"]);
+        assert!(world
+            .resource::<SatelliteManager>()
+            .orbit_entities
+            .contains(&shared));
+    }
+}
@@ -1,33 +1,94 @@
 use crate::prelude::*;
 use chrono::{DateTime, Utc};
 
+mod clock;
 mod communication;
+mod compute;
+mod earth;
+mod ephemeris;
+mod ground_station;
+mod link_budget;
+mod link_log;
 mod manager;
+mod network;
 mod orbit;
+mod plus_grid;
+mod power;
+mod recorder;
+mod rng;
+mod routing;
 mod satellite;
+mod sgp4;
+mod spatial;
+mod traffic;
 
+use clock::*;
 use communication::*;
+use compute::*;
+use earth::*;
+use ephemeris::*;
+use ground_station::*;
+use link_log::*;
 use manager::*;
+use network::*;
 use orbit::*;
+use power::*;
+use recorder::*;
+use rng::*;
+use routing::*;
 use satellite::*;
-
-pub use manager::{AttachSatellites, SpawnOrbits, SpawnSatellites};
+use sgp4::*;
+use spatial::*;
+use traffic::*;
+
+pub use clock::SimulationClock;
+pub use communication::Connections;
+pub use compute::{dispatch_task, ComputeStats, Offloading, Task, TaskState};
+pub use earth::{eci_to_ecef_velocity, transform_to_ecef, transform_to_geodetic, Earth, Geodetic};
+pub use ephemeris::{Ephemeris, Sunlight};
+pub use link_budget::{LinkBudget, LinkBudgets};
+pub use link_log::{LinkEvent, LinkLog};
+pub use manager::{AttachSatellites, RemoveOrbits, RemoveSatellites, SpawnOrbits, SpawnSatellites};
+pub use network::{NetworkGraph, Path};
 pub use orbit::{Orbit, ToggleOrbitGizmos};
-pub use satellite::Satellite;
+pub use power::PowerSystem;
+pub use routing::Routing;
+pub use satellite::{Following, Satellite, Velocity};
+pub use sgp4::Sgp4Propagator;
+pub use traffic::Traffic;
 
 const FACTOR: f32 = 73.594_6; // u^(1/3)
 
+/// Whether the app runs without a window, so display systems are left out.
+fn is_headless(app: &App) -> bool {
+    app.world().contains_resource::<Headless>()
+}
+
 /// A Core Plugin for the simulator.
 pub struct CorePlugin;
 
 impl Plugin for CorePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
+            ClockPlugin,
             CommunicationPlugin,
+            ComputePlugin,
+            EarthPlugin,
+            EphemerisPlugin,
+            GroundStationPlugin,
             ManagerPlugin,
+            NetworkPlugin,
             SatellitePlugin,
             OrbitPlugin,
-        ));
+            PowerPlugin,
+            RoutingPlugin,
+            Sgp4Plugin,
+            SpatialPlugin,
+            TrafficPlugin,
+        ))
+        .add_plugins((LinkLogPlugin, RecorderPlugin));
+
+        app.init_resource::<SimulationRng>();
 
         app.add_systems(Startup, setup);
     }
@@ -43,32 +104,45 @@ pub struct OrbitalElements {
     longitude_of_ascending_node: f32, // 升交点赤经(rad)
     argument_of_periapsis: f32,       // 近地点角距(rad)
     mean_anomaly: f32,                // 平近点角(rad)
+    sgp4: Option<Sgp4Propagator>,     // 使用SGP4传播时的模型
 }
 
 impl OrbitalElements {
     /// Create a new `OrbitalElements` instance from raw satellite data.
     /// By convert the units of the data. And update the mean anomaly by the current time.
-    fn from_raw_sate_data(raw_data: RawSatelliteData, current_time: DateTime<Utc>) -> Result<Self> {
+    /// With `Propagator::Sgp4`, the full record is kept to drive the satellite by SGP4.
+    fn from_raw_sate_data(
+        raw_data: RawSatelliteData,
+        current_time: DateTime<Utc>,
+        propagator: Propagator,
+    ) -> Result<Self> {
         let observe_time = parse_time_from_str(&raw_data.epoch)?;
 
+        let sgp4 = match propagator {
+            Propagator::Kepler => None,
+            Propagator::Sgp4 => Some(Sgp4Propagator::from_raw_sate_data(&raw_data, current_time)?),
+        };
+
         // convert units
         let mut orbit_elements = Self {
-            mean_motion: raw_data.mean_motion * 2. * PI / 86400.0, // rev/day to rad/s
-            eccentricity: raw_data.eccentricity,
-            inclination: raw_data.inclination * PI / 180.0, // degrees to rad
-            argument_of_periapsis: raw_data.arg_of_pericenter * PI / 180.0, // degrees to rad
-            longitude_of_ascending_node: raw_data.ra_of_asc_node * PI / 180.0, // degrees to rad
-            mean_anomaly: raw_data.mean_anomaly * PI / 180.0, // degrees to rad
+            mean_motion: raw_data.mean_motion as f32 * 2. * PI / 86400.0, // rev/day to rad/s
+            eccentricity: raw_data.eccentricity as f32,
+            inclination: raw_data.inclination as f32 * PI / 180.0, // degrees to rad
+            argument_of_periapsis: raw_data.arg_of_pericenter as f32 * PI / 180.0, // degrees to rad
+            longitude_of_ascending_node: raw_data.ra_of_asc_node as f32 * PI / 180.0, // degrees to rad
+            mean_anomaly: raw_data.mean_anomaly as f32 * PI / 180.0, // degrees to rad
+            sgp4,
         };
 
-        // update mean anomaly by current time
-        let duration = current_time - observe_time;
-        orbit_elements.mean_anomaly +=
-            (duration.num_seconds() as f32 * orbit_elements.mean_motion) % (2. * PI);
+        // update mean anomaly by current time, in f64 to keep precision over long durations
+        let duration = (current_time - observe_time).num_milliseconds() as f64 / 1000.0;
+        let mean_motion = raw_data.mean_motion * std::f64::consts::TAU / 86400.0;
+        let mean_anomaly = raw_data.mean_anomaly.to_radians() + mean_motion * duration;
+        orbit_elements.mean_anomaly = mean_anomaly.rem_euclid(std::f64::consts::TAU) as f32;
         Ok(orbit_elements)
     }
 
-    fn sep_out_mean_anomaly(self) -> (Orbit, f32) {
+    fn sep_out_mean_anomaly(self) -> (Orbit, f32, Option<Sgp4Propagator>) {
         (
             Orbit {
                 mean_motion: self.mean_motion,
@@ -78,6 +152,7 @@ impl OrbitalElements {
                 argument_of_periapsis: self.argument_of_periapsis,
             },
             self.mean_anomaly,
+            self.sgp4,
         )
     }
 
@@ -90,6 +165,7 @@ impl OrbitalElements {
             longitude_of_ascending_node: data[3],
             argument_of_periapsis: data[4],
             mean_anomaly: data[5],
+            sgp4: None,
         };
         if sate.eccentricity < 0.0 || sate.eccentricity >= 1.0 {
             return Err("Invalid eccentricity".to_string());
@@ -101,6 +177,7 @@ impl OrbitalElements {
 /// Read satellite data and Setup Satellite Manager.
 fn setup(
     config: Res<Config>,
+    clock: Res<SimulationClock>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut manager: ResMut<SatelliteManager>,
@@ -115,14 +192,18 @@ fn setup(
 
     // read satellite data
     let data = if let Some(dataset) = &config.dataset {
-        let current_time = Utc::now();
+        let current_time = clock.epoch();
 
         dataset
             .read_from_file()?
             .into_iter()
             .map(|satellite_data| {
                 let object_id = satellite_data.object_id.clone();
-                let satellite = OrbitalElements::from_raw_sate_data(satellite_data, current_time)?;
+                let satellite = OrbitalElements::from_raw_sate_data(
+                    satellite_data,
+                    current_time,
+                    dataset.propagator,
+                )?;
                 Ok((object_id, satellite))
             })
             .collect::<Result<Vec<(String, OrbitalElements)>>>()?
@@ -134,3 +215,23 @@ fn setup(
 
     Ok(())
 }
+
+/// A headless app with the core plugins, advancing `steps_per_update` fixed steps per update.
+#[cfg(test)]
+pub(crate) fn test_app(config: &str, steps_per_update: u32) -> App {
+    use bevy::time::TimeUpdateStrategy;
+
+    let config: Config = toml::from_str(config).unwrap();
+    let timestep = Time::<Fixed>::default().timestep();
+    let mut app = App::new();
+    app.insert_resource(config)
+        .insert_resource(Headless)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            timestep * steps_per_update,
+        ))
+        .add_plugins((MinimalPlugins, TransformPlugin, AssetPlugin::default()))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins(CorePlugin);
+    app
+}
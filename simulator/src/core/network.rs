@@ -0,0 +1,283 @@
+use bevy::ecs::entity::EntityHashMap;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use super::*;
+
+pub(super) const SPEED_OF_LIGHT: f64 = 299_792.458; // 光速(km/s)
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkGraph>().add_systems(
+            FixedPostUpdate,
+            rebuild_network_graph.after(update_ground_links),
+        );
+    }
+}
+
+/// The current network: satellites and ground stations, linked by ISLs in `Connections`
+/// and ground links in `GroundLinks`, weighted by the one-way propagation delay.
+/// Rebuilt every fixed tick.
+#[derive(Resource, Default, Clone)]
+pub struct NetworkGraph {
+    nodes: Vec<Entity>,
+    indices: EntityHashMap<usize>,
+    edges: Vec<Vec<(usize, f64)>>, // (邻居, 传播时延(s))
+}
+
+/// A path through the network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub nodes: Vec<Entity>,
+    pub delay: f64, // 总传播时延(s)
+}
+
+impl NetworkGraph {
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.indices.clear();
+        self.edges.clear();
+    }
+
+    fn add_node(&mut self, entity: Entity) -> usize {
+        *self.indices.entry(entity).or_insert_with(|| {
+            self.nodes.push(entity);
+            self.edges.push(vec![]);
+            self.nodes.len() - 1
+        })
+    }
+
+    /// Add a link in one direction.
+    fn add_edge(&mut self, from: Entity, to: Entity, delay: f64) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        self.edges[from].push((to, delay));
+    }
+
+    /// Add a link in both directions.
+    pub(super) fn link(&mut self, a: Entity, b: Entity, delay: f64) {
+        self.add_edge(a, b, delay);
+        self.add_edge(b, a, delay);
+    }
+
+    /// Delay of the link between two nodes.
+    pub fn delay(&self, from: Entity, to: Entity) -> Option<f64> {
+        let &from = self.indices.get(&from)?;
+        let &to = self.indices.get(&to)?;
+        let delay = self.delay_between(from, to);
+        delay.is_finite().then_some(delay)
+    }
+
+    /// Nodes linked to `node`, with the delays of the links.
+    pub fn neighbours(&self, node: Entity) -> impl Iterator<Item = (Entity, f64)> + '_ {
+        self.indices
+            .get(&node)
+            .into_iter()
+            .flat_map(|&i| &self.edges[i])
+            .map(|&(next, delay)| (self.nodes[next], delay))
+    }
+
+    /// Every link once, with its delay.
+    pub fn links(&self) -> impl Iterator<Item = (Entity, Entity, f64)> + '_ {
+        self.edges.iter().enumerate().flat_map(move |(i, edges)| {
+            edges
+                .iter()
+                .filter(move |(j, _)| i < *j)
+                .map(move |&(j, delay)| (self.nodes[i], self.nodes[j], delay))
+        })
+    }
+
+    /// Nodes within `hops` hops of `node` (itself included), nearest first.
+    pub fn within_hops(&self, node: Entity, hops: usize) -> Vec<Entity> {
+        let Some(&start) = self.indices.get(&node) else {
+            return vec![];
+        };
+        let mut visited = vec![false; self.nodes.len()];
+        visited[start] = true;
+        let mut frontier = vec![start];
+        let mut found = vec![node];
+        for _ in 0..hops {
+            let mut next_frontier = vec![];
+            for i in frontier {
+                for &(next, _) in &self.edges[i] {
+                    if !visited[next] {
+                        visited[next] = true;
+                        next_frontier.push(next);
+                        found.push(self.nodes[next]);
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+        found
+    }
+
+    /// Total delay along the nodes.
+    pub fn path_delay(&self, nodes: &[Entity]) -> f64 {
+        nodes
+            .windows(2)
+            .map(|pair| self.delay(pair[0], pair[1]).unwrap_or(f64::INFINITY))
+            .sum()
+    }
+
+    /// The minimum-latency path by Dijkstra.
+    pub fn shortest_path(&self, from: Entity, to: Entity) -> Option<Path> {
+        self.shortest_path_by(from, to, |_, _, delay| Some(delay))
+    }
+
+    /// The path with the minimum total weight of its links, by Dijkstra.
+    /// `weight(from, to, delay)` gives the non-negative weight of a link, or `None` to skip it.
+    pub fn shortest_path_by(
+        &self,
+        from: Entity,
+        to: Entity,
+        weight: impl Fn(Entity, Entity, f64) -> Option<f64>,
+    ) -> Option<Path> {
+        let &source = self.indices.get(&from)?;
+        let &target = self.indices.get(&to)?;
+
+        let mut cost = vec![f64::INFINITY; self.nodes.len()];
+        let mut previous = vec![usize::MAX; self.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        cost[source] = 0.0;
+        heap.push(State {
+            cost: 0.0,
+            node: source,
+        });
+        while let Some(State { cost: c, node }) = heap.pop() {
+            if node == target {
+                break;
+            }
+            if c > cost[node] {
+                continue;
+            }
+            for &(next, delay) in &self.edges[node] {
+                let Some(link_weight) = weight(self.nodes[node], self.nodes[next], delay) else {
+                    continue;
+                };
+                let next_cost = c + link_weight;
+                if next_cost < cost[next] {
+                    cost[next] = next_cost;
+                    previous[next] = node;
+                    heap.push(State {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        if cost[target].is_infinite() {
+            return None;
+        }
+
+        let mut nodes = vec![target];
+        while let Some(&node) = nodes.last() {
+            if node == source {
+                break;
+            }
+            nodes.push(previous[node]);
+        }
+        nodes.reverse();
+        let delay = nodes
+            .windows(2)
+            .map(|pair| self.delay_between(pair[0], pair[1]))
+            .sum();
+        Some(Path {
+            nodes: nodes.into_iter().map(|i| self.nodes[i]).collect(),
+            delay,
+        })
+    }
+
+    fn delay_between(&self, from: usize, to: usize) -> f64 {
+        self.edges[from]
+            .iter()
+            .filter(|(next, _)| *next == to)
+            .map(|(_, delay)| *delay)
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+/// Min-heap entry for Dijkstra.
+struct State {
+    cost: f64,
+    node: usize,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for a min-heap, ties broken by node for determinism
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// One-way propagation delay (s) over a distance (km).
+pub(super) fn propagation_delay(distance: f64) -> f64 {
+    distance / SPEED_OF_LIGHT
+}
+
+pub(super) fn rebuild_network_graph(
+    mut graph: ResMut<NetworkGraph>,
+    satellites: Query<(Entity, &Connections, &LinkBudgets), With<Satellite>>,
+    ground_stations: Query<(Entity, &GroundLinks, &Transform)>,
+    positions: Query<&Transform, With<Satellite>>,
+) {
+    graph.clear();
+    for (sat, conns, budgets) in &satellites {
+        graph.add_node(sat);
+        for other in &conns.connections {
+            if let Some(budget) = budgets.0.get(other) {
+                graph.add_edge(sat, *other, budget.delay);
+            }
+        }
+    }
+    for (station, links, transform) in &ground_stations {
+        graph.add_node(station);
+        for &sat in &links.satellites {
+            let Ok(sat_transform) = positions.get(sat) else {
+                continue;
+            };
+            let distance = transform.translation.distance(sat_transform.translation) as f64;
+            graph.link(station, sat, propagation_delay(distance));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shortest_path() {
+        let [a, b, c, d] = [0, 1, 2, 3].map(Entity::from_raw);
+        let mut graph = NetworkGraph::default();
+        graph.link(a, b, 0.010);
+        graph.link(b, d, 0.010);
+        graph.link(a, c, 0.001);
+        graph.link(c, d, 0.001);
+
+        let path = graph.shortest_path(a, d).unwrap();
+        assert_eq!(path.nodes, vec![a, c, d]);
+        assert!((path.delay - 0.002).abs() < 1e-12);
+
+        graph.add_node(Entity::from_raw(4));
+        assert!(graph.shortest_path(a, Entity::from_raw(4)).is_none());
+    }
+}
@@ -1,18 +1,36 @@
-use bevy::ecs::entity_disabling::Disabled;
+use bevy::ecs::{entity_disabling::Disabled, relationship::RelationshipTarget};
+
+use std::f64::consts::TAU;
 
 use super::*;
 
+const J2: f32 = 1.082_626_7e-3; // 地球J2项
+const EQUATORIAL_RADIUS: f32 = 6378.137; // 地球赤道半径(km)
+const GIZMO_REDRAW_DRIFT: f32 = 0.5 * PI / 180.0; // RAAN drift before redrawing gizmos (rad)
+
 pub struct OrbitPlugin;
 
 impl Plugin for OrbitPlugin {
     fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            apply_j2_precession.run_if(|config: Res<Config>| config.simulation.j2),
+        );
+        if is_headless(app) {
+            return;
+        }
         app.add_observer(update_orbit_gizmos)
             .add_observer(toggle_orbit_gizmos)
-            .add_systems(Startup, setup);
+            .add_systems(Startup, setup)
+            .add_systems(
+                FixedUpdate,
+                redraw_drifted_orbits.after(apply_j2_precession),
+            );
     }
 }
 
 #[derive(Component, Clone)]
+#[require(DrawnRaan)]
 pub struct Orbit {
     pub mean_motion: f32,                 // 平均运动(rad/s)
     pub eccentricity: f32,                // 离心率
@@ -21,9 +39,53 @@ pub struct Orbit {
     pub argument_of_periapsis: f32,       // 近地点角距(rad)
 }
 
+/// Secular rates caused by J2.
+pub struct J2Rates {
+    pub longitude_of_ascending_node: f32, // 升交点赤经变化率(rad/s)
+    pub argument_of_periapsis: f32,       // 近地点角距变化率(rad/s)
+    pub mean_anomaly: f32,                // 平近点角变化率修正(rad/s)
+}
+
+impl Orbit {
+    /// From mean motion, eccentricity, inclination, RAAN and argument of periapsis.
+    pub fn from_slice(data: &[f32; 5]) -> Result<Self, String> {
+        let orbit = Self {
+            mean_motion: data[0],
+            eccentricity: data[1],
+            inclination: data[2],
+            longitude_of_ascending_node: data[3],
+            argument_of_periapsis: data[4],
+        };
+        if orbit.eccentricity < 0.0 || orbit.eccentricity >= 1.0 {
+            return Err("Invalid eccentricity".to_string());
+        }
+        Ok(orbit)
+    }
+
+    /// Secular J2 rates of RAAN, argument of periapsis and mean anomaly.
+    pub fn j2_rates(&self) -> J2Rates {
+        let n = self.mean_motion;
+        let semi_major_axis = FACTOR * n.powf(-2. / 3.);
+        let p = semi_major_axis * (1.0 - self.eccentricity.powi(2));
+        let k = 0.75 * n * J2 * (EQUATORIAL_RADIUS / p).powi(2);
+        let cos_i = self.inclination.cos();
+        J2Rates {
+            longitude_of_ascending_node: -2.0 * k * cos_i,
+            argument_of_periapsis: k * (5.0 * cos_i * cos_i - 1.0),
+            mean_anomaly: k
+                * (1.0 - self.eccentricity.powi(2)).sqrt()
+                * (3.0 * cos_i * cos_i - 1.0),
+        }
+    }
+}
+
 #[derive(Component)]
 struct OrbitGizmos;
 
+/// RAAN of the orbit when the orbit gizmos were last drawn.
+#[derive(Component, Default)]
+struct DrawnRaan(f32);
+
 #[derive(Event)]
 pub struct OrbitChanged;
 
@@ -38,17 +100,74 @@ fn update_orbit_gizmos(
     _trigger: Trigger<OrbitChanged>,
     gizmos: Single<(&mut Gizmo, Has<Disabled>), With<OrbitGizmos>>,
     mut gizmo_assets: ResMut<Assets<GizmoAsset>>,
-    orbits: Query<&Orbit>,
+    orbits: Query<(&Orbit, &mut DrawnRaan)>,
 ) {
     info!("update_orbit_gizmo");
     let mut gizmo = GizmoAsset::default();
-    for orbit in orbits {
+    for (orbit, mut drawn_raan) in orbits {
         draw_orbit_gizmo(orbit, &mut gizmo);
+        drawn_raan.0 = orbit.longitude_of_ascending_node;
     }
     let (mut gizmos, _) = gizmos.into_inner();
     gizmos.handle = gizmo_assets.add(gizmo);
 }
 
+/// RAAN and argument of periapsis at the clock epoch, from which J2 precession is computed.
+#[derive(Component)]
+struct EpochOrientation {
+    longitude_of_ascending_node: f64,
+    argument_of_periapsis: f64,
+}
+
+/// Set RAAN and argument of periapsis by the J2 secular rates at the current clock time.
+/// Orbits followed by SGP4 satellites are skipped, SGP4 already includes J2.
+fn apply_j2_precession(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut orbits: Query<(
+        Entity,
+        &mut Orbit,
+        Option<&EpochOrientation>,
+        Option<&FollowedBy>,
+    )>,
+    sgp4_satellites: Query<(), With<Sgp4Propagator>>,
+) {
+    let elapsed = clock.elapsed_secs();
+    for (entity, mut orbit, epoch_orientation, sates) in &mut orbits {
+        if sates.is_some_and(|sates| sates.iter().any(|sate| sgp4_satellites.contains(sate))) {
+            continue;
+        }
+        let rates = orbit.j2_rates();
+        let Some(epoch_orientation) = epoch_orientation else {
+            commands.entity(entity).insert(EpochOrientation {
+                longitude_of_ascending_node: orbit.longitude_of_ascending_node as f64
+                    - rates.longitude_of_ascending_node as f64 * elapsed,
+                argument_of_periapsis: orbit.argument_of_periapsis as f64
+                    - rates.argument_of_periapsis as f64 * elapsed,
+            });
+            continue;
+        };
+        orbit.longitude_of_ascending_node = (epoch_orientation.longitude_of_ascending_node
+            + rates.longitude_of_ascending_node as f64 * elapsed)
+            .rem_euclid(TAU) as f32;
+        orbit.argument_of_periapsis = (epoch_orientation.argument_of_periapsis
+            + rates.argument_of_periapsis as f64 * elapsed)
+            .rem_euclid(TAU) as f32;
+    }
+}
+
+/// Redraw the orbit gizmos once any orbit's RAAN drifted away from the drawn one.
+fn redraw_drifted_orbits(mut commands: Commands, orbits: Query<(&Orbit, &DrawnRaan)>) {
+    let drifted = orbits.iter().any(|(orbit, drawn_raan)| {
+        let drift =
+            (orbit.longitude_of_ascending_node - drawn_raan.0 + PI).rem_euclid(2. * PI) - PI;
+        drift.abs() > GIZMO_REDRAW_DRIFT
+    });
+    if drifted {
+        commands.trigger(OrbitChanged);
+    }
+}
+
 fn toggle_orbit_gizmos(
     _trigger: Trigger<ToggleOrbitGizmos>,
     gizmos: Single<(Entity, Has<Disabled>), With<OrbitGizmos>>,
@@ -89,3 +208,35 @@ fn draw_orbit_gizmo(elements: &Orbit, gizmo: &mut GizmoAsset) {
 
     gizmo.ellipse(iso, half_size, Color::srgba(1., 1., 1., 0.01));
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A circular orbit at `altitude`(km) above the equatorial radius.
+    fn circular(altitude: f32, inclination: f32) -> Orbit {
+        let semi_major_axis = EQUATORIAL_RADIUS + altitude;
+        let mean_motion = (FACTOR.powi(3) / semi_major_axis.powi(3)).sqrt();
+        Orbit::from_slice(&[mean_motion, 0.0, inclination.to_radians(), 0.0, 0.0]).unwrap()
+    }
+
+    #[test]
+    fn test_j2_rates() {
+        let degrees_per_day = |rate: f32| rate.to_degrees() * 86400.0;
+
+        // sun-synchronous at 700 km: the plane follows the Sun, 360° a year
+        let rates = circular(700.0, 98.19).j2_rates();
+        let raan = degrees_per_day(rates.longitude_of_ascending_node);
+        assert!((raan - 0.9856).abs() < 0.01, "{}", raan);
+
+        // prograde orbits regress
+        let rates = circular(550.0, 53.0).j2_rates();
+        assert!(degrees_per_day(rates.longitude_of_ascending_node) < -4.0);
+
+        // the apsides are frozen at the critical inclination, 63.43°
+        let rates = circular(700.0, 63.4349).j2_rates();
+        let argp = degrees_per_day(rates.argument_of_periapsis);
+        assert!(argp.abs() < 1e-3, "{}", argp);
+        assert!(degrees_per_day(circular(700.0, 53.0).j2_rates().argument_of_periapsis) > 0.0);
+    }
+}
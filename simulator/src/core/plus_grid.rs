@@ -0,0 +1,263 @@
+use bevy::ecs::{entity::EntityHashMap, relationship::Relationship};
+use std::f32::consts::TAU;
+
+use super::{link_log::*, *};
+
+/// A satellite, with the elements that place it in a plane.
+#[derive(Clone, Copy)]
+struct Member {
+    entity: Entity,
+    inclination: f32,          // 轨道倾角(rad)
+    raan: f32,                 // 升交点赤经(rad), in [0, 2π)
+    argument_of_latitude: f32, // 纬度幅角(rad)
+    position: Vec3,
+}
+
+/// Satellites of one orbital plane, sorted by argument of latitude.
+struct Plane {
+    satellites: Vec<(Entity, Vec3)>,
+}
+
+/// Group satellites into shells of close inclinations, and each shell into planes of close
+/// RAANs, sorted by RAAN. Satellites do not need to share an `Orbit` entity, datasets spawn one
+/// per satellite.
+fn group_planes(mut members: Vec<Member>, tolerance: f32) -> Vec<Vec<Plane>> {
+    members.sort_by(|a, b| a.inclination.total_cmp(&b.inclination));
+    let mut shells = vec![];
+    for shell in members.chunk_by_mut(|a, b| b.inclination - a.inclination <= tolerance) {
+        shell.sort_by(|a, b| a.raan.total_cmp(&b.raan));
+        let mut planes: Vec<Vec<Member>> = shell
+            .chunk_by(|a, b| b.raan - a.raan <= tolerance)
+            .map(<[Member]>::to_vec)
+            .collect();
+        // a plane across RAAN 0
+        if planes.len() > 1 {
+            let first = planes[0][0].raan;
+            let last = planes[planes.len() - 1].last().unwrap().raan;
+            if first + TAU - last <= tolerance {
+                let first = planes.remove(0);
+                planes.last_mut().unwrap().extend(first);
+            }
+        }
+        shells.push(
+            planes
+                .into_iter()
+                .map(|mut plane| {
+                    plane.sort_by(|a, b| a.argument_of_latitude.total_cmp(&b.argument_of_latitude));
+                    Plane {
+                        satellites: plane.iter().map(|m| (m.entity, m.position)).collect(),
+                    }
+                })
+                .collect(),
+        );
+    }
+    shells
+}
+
+/// Rebuild the +Grid topology: the in-plane predecessor and successor, and the nearest
+/// satellites in the two adjacent planes (sorted by RAAN) of the same shell, all within
+/// `connection_distance`.
+/// Planes are grouped by inclination and RAAN. Satellites low on power are left out.
+pub(super) fn update_plus_grid(
+    config: Res<Config>,
+    orbits: Query<&Orbit>,
+    satellites: Query<(Entity, &Satellite, &Following, &Transform), Without<LowPower>>,
+    low_power: Query<(), With<LowPower>>,
+    mut connections: Query<(Entity, &mut Connections)>,
+    mut link_events: LinkEvents,
+) {
+    let options = &config.simulation.plus_grid;
+    let min_radius = EARTH_RADIUS + config.simulation.grazing_altitude;
+    let max_distance_sq = config.simulation.connection_distance.powi(2);
+    let in_range = |a: Vec3, b: Vec3| {
+        a.distance_squared(b) <= max_distance_sq && line_of_sight(a, b, min_radius)
+    };
+
+    let members = satellites
+        .iter()
+        .filter_map(|(entity, satellite, following, transform)| {
+            let orbit = orbits.get(following.get()).ok()?;
+            Some(Member {
+                entity,
+                inclination: orbit.inclination,
+                raan: orbit.longitude_of_ascending_node.rem_euclid(TAU),
+                argument_of_latitude: (orbit.argument_of_periapsis + satellite.mean_anomaly)
+                    .rem_euclid(TAU),
+                position: transform.translation,
+            })
+        })
+        .collect();
+    let shells = group_planes(members, options.plane_tolerance.to_radians());
+
+    let mut links: EntityHashMap<Vec<Entity>> = default();
+    let mut link = |a: Entity, b: Entity| {
+        links.entry(a).or_default().push(b);
+        links.entry(b).or_default().push(a);
+    };
+
+    let in_polar_region = |position: Vec3| {
+        options.polar_latitude.is_some_and(|latitude| {
+            (position.z / position.length()).asin().abs() > latitude.to_radians()
+        })
+    };
+    for planes in &shells {
+        // in-plane: a ring of predecessor and successor
+        for plane in planes {
+            let n = plane.satellites.len();
+            let ring = if n > 2 { n } else { n - 1 };
+            for i in 0..ring {
+                let (a, b) = (plane.satellites[i], plane.satellites[(i + 1) % n]);
+                if in_range(a.1, b.1) {
+                    link(a.0, b.0);
+                }
+            }
+        }
+
+        // cross-plane: match the nearest satellites of adjacent planes
+        let n = planes.len();
+        let pairs = if options.cross_seam && n > 2 {
+            n
+        } else {
+            n.saturating_sub(1)
+        };
+        for k in 0..pairs {
+            let (west, east) = (&planes[k], &planes[(k + 1) % n]);
+            let mut candidates: Vec<(usize, usize, f32)> = west
+                .satellites
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, a))| !in_polar_region(*a))
+                .flat_map(|(i, (_, a))| {
+                    east.satellites
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, b))| !in_polar_region(*b))
+                        .filter(|(_, (_, b))| in_range(*a, *b))
+                        .map(move |(j, (_, b))| (i, j, a.distance_squared(*b)))
+                })
+                .collect();
+            candidates.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+            // each satellite has at most one link to each side
+            let mut west_linked = vec![false; west.satellites.len()];
+            let mut east_linked = vec![false; east.satellites.len()];
+            for (i, j, _) in candidates {
+                if west_linked[i] || east_linked[j] {
+                    continue;
+                }
+                west_linked[i] = true;
+                east_linked[j] = true;
+                link(west.satellites[i].0, east.satellites[j].0);
+            }
+        }
+    }
+
+    for (sate, mut conns) in &mut connections {
+        let desired = links.remove(&sate).unwrap_or_default();
+        if conns.connections == desired {
+            continue;
+        }
+        for &other in conns
+            .connections
+            .iter()
+            .filter(|other| !desired.contains(other))
+        {
+            let position = |sate| satellites.get(sate).ok().map(|(.., t)| t.translation);
+            let reason = if low_power.contains(sate) || low_power.contains(other) {
+                LinkDownReason::LowPower
+            } else if let (Some(a), Some(b)) = (position(sate), position(other)) {
+                if a.distance_squared(b) > max_distance_sq {
+                    LinkDownReason::OutOfRange
+                } else if !line_of_sight(a, b, min_radius) {
+                    LinkDownReason::Occluded
+                } else if in_polar_region(a) || in_polar_region(b) {
+                    LinkDownReason::PolarRegion
+                } else {
+                    LinkDownReason::Capacity
+                }
+            } else {
+                LinkDownReason::Manual
+            };
+            link_events.down(sate, other, reason);
+        }
+        for &other in desired
+            .iter()
+            .filter(|other| !conns.connections.contains(other))
+        {
+            link_events.up(sate, other, LinkKind::Isl);
+        }
+        conns.connections = desired;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test_app, *};
+
+    #[test]
+    fn test_walker_shell() {
+        const CONFIG: &str = r#"
+            [Network]
+            port = 0
+            [Display]
+            orbit = false
+            connection = false
+            [Simulation]
+            start_time = "2025-05-01T00:00:00"
+            time_speed = 1.0
+            connection_distance = 5000.0
+            connection_number = 4
+            topology = "plus_grid"
+            seed = 42
+            [Simulation.plus_grid]
+            cross_seam = true
+        "#;
+        // Walker 53°: 144/12/1 at 550 km, spawned as one orbit per satellite like a dataset
+        let (planes, per_plane) = (12, 12);
+        let semi_major_axis: f32 = EARTH_RADIUS + 550.0;
+        let mean_motion = (FACTOR.powi(3) / semi_major_axis.powi(3)).sqrt();
+        let satellites = (0..planes)
+            .flat_map(|p| (0..per_plane).map(move |s| (p, s)))
+            .map(|(p, s)| {
+                let elements = OrbitalElements {
+                    mean_motion,
+                    eccentricity: 0.0,
+                    inclination: 53f32.to_radians(),
+                    longitude_of_ascending_node: TAU * p as f32 / planes as f32,
+                    argument_of_periapsis: 0.0,
+                    mean_anomaly: TAU * s as f32 / per_plane as f32
+                        + TAU * p as f32 / (planes * per_plane) as f32,
+                    sgp4: None,
+                };
+                (format!("P{}-{}", p, s), elements)
+            })
+            .collect();
+
+        let mut app = test_app(CONFIG, 1);
+        app.world_mut()
+            .resource_mut::<SatelliteManager>()
+            .add_satellites(satellites);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let world = app.world_mut();
+        assert_eq!(
+            world.query::<&Orbit>().iter(world).count(),
+            planes * per_plane
+        );
+        let mut checked = 0;
+        for (name, conns, transform) in world
+            .query::<(&Name, &Connections, &Transform)>()
+            .iter(world)
+        {
+            let position = transform.translation;
+            let latitude = (position.z / position.length()).asin().to_degrees();
+            if latitude.abs() < 30.0 {
+                assert_eq!(conns.connections.len(), 4, "{} at {:.1}°", name, latitude);
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::ecs::entity::EntityHashMap;
+use serde::Serialize;
+
+use super::{ground_station::GroundLinks, *};
+
+/// Number of colours from an empty to a full battery.
+const SOC_COLORS: usize = 11;
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, insert_power_systems)
+            .add_systems(FixedPostUpdate, update_power.after(rebuild_network_graph));
+        if !is_headless(app) {
+            app.add_systems(Startup, setup)
+                .add_systems(Update, color_satellites);
+        }
+    }
+}
+
+/// Battery, solar panels and loads of a satellite.
+#[derive(Component, Debug, Clone, Serialize)]
+pub struct PowerSystem {
+    pub capacity: f64,    // 电池容量(Wh)
+    pub charge: f64,      // 剩余电量(Wh)
+    pub solar_power: f64, // 光照下发电功率(W)
+    pub base_load: f64,   // 基础功耗(W)
+    pub consumption: f64, // 当前总功耗(W)
+    pub in_eclipse: bool,
+    #[serde(skip)]
+    updated: Option<f64>, // 上次更新的仿真时间(s)
+    #[serde(skip)]
+    jumps: u32, // 上次更新时的时钟跳转次数
+}
+
+impl PowerSystem {
+    fn new(config: &PowerConfig) -> Self {
+        Self {
+            capacity: config.battery_capacity,
+            charge: config.battery_capacity * config.initial_soc.clamp(0.0, 1.0),
+            solar_power: config.solar_power,
+            base_load: config.base_load,
+            consumption: config.base_load,
+            in_eclipse: false,
+            updated: None,
+            jumps: 0,
+        }
+    }
+
+    /// Charge by `generation`(W) less the consumption over `duration`(s), and whether the
+    /// satellite is low on power afterwards. It stays low from below `shed_soc` until it
+    /// recharges to `restore_soc`.
+    fn step(
+        &mut self,
+        generation: f64,
+        duration: f64,
+        low_power: bool,
+        config: &PowerConfig,
+    ) -> bool {
+        self.charge = (self.charge + (generation - self.consumption) * duration / 3600.0)
+            .clamp(0.0, self.capacity);
+        let soc = self.state_of_charge();
+        if low_power {
+            soc < config.restore_soc
+        } else {
+            soc < config.shed_soc
+        }
+    }
+
+    /// State of charge, from 0 to 1.
+    pub fn state_of_charge(&self) -> f64 {
+        if self.capacity > 0.0 {
+            self.charge / self.capacity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A satellite low on power. It keeps no links until it recharges.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct LowPower;
+
+/// Materials colouring satellites by state of charge.
+#[derive(Resource)]
+struct SocMaterials(Vec<Handle<StandardMaterial>>);
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    let palette = (0..SOC_COLORS)
+        .map(|i| {
+            let soc = i as f32 / (SOC_COLORS - 1) as f32;
+            materials.add(StandardMaterial {
+                // red to green
+                base_color: Color::hsl(120.0 * soc, 1.0, 0.5),
+                unlit: true,
+                ..default()
+            })
+        })
+        .collect();
+    commands.insert_resource(SocMaterials(palette));
+}
+
+fn insert_power_systems(
+    mut commands: Commands,
+    config: Res<Config>,
+    satellites: Query<Entity, (With<Satellite>, Without<PowerSystem>)>,
+) {
+    for sate in &satellites {
+        commands
+            .entity(sate)
+            .insert(PowerSystem::new(&config.power));
+    }
+}
+
+/// Charge from the solar panels out of the Earth's shadow, discharge by the loads of links and
+/// running tasks, and shed the links of satellites low on power.
+fn update_power(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    config: Res<Config>,
+    ephemeris: Res<Ephemeris>,
+    mut satellites: Query<(
+        Entity,
+        &Transform,
+        &Connections,
+        Option<&ComputeNode>,
+        &mut PowerSystem,
+        Has<LowPower>,
+    )>,
+    ground_stations: Query<&GroundLinks>,
+) {
+    let config = &config.power;
+    let now = clock.elapsed_secs();
+
+    let mut ground_links: EntityHashMap<usize> = default();
+    for links in &ground_stations {
+        for &sat in &links.satellites {
+            *ground_links.entry(sat).or_default() += 1;
+        }
+    }
+
+    for (sate, transform, conns, compute, mut power, low_power) in &mut satellites {
+        power.in_eclipse = ephemeris.in_shadow(transform.translation.as_dvec3());
+        power.consumption = power.base_load
+            + config.isl_power * conns.connections.len() as f64
+            + config.ground_link_power * ground_links.get(&sate).copied().unwrap_or(0) as f64;
+        if compute.is_some_and(|compute| compute.is_busy()) {
+            power.consumption += config.compute_power;
+        }
+        let generation = if power.in_eclipse {
+            0.0
+        } else {
+            power.solar_power
+        };
+
+        // no charge is carried across a jump of the clock, in either direction, as the
+        // satellite was not simulated in between
+        let duration = match power.updated {
+            Some(last) if power.jumps == clock.jumps() => (now - last).max(0.0),
+            _ => 0.0,
+        };
+        power.updated = Some(now);
+        power.jumps = clock.jumps();
+
+        match (
+            low_power,
+            power.step(generation, duration, low_power, config),
+        ) {
+            (false, true) => {
+                commands.entity(sate).insert(LowPower);
+            }
+            (true, false) => {
+                commands.entity(sate).remove::<LowPower>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn color_satellites(
+    config: Res<Config>,
+    manager: Res<SatelliteManager>,
+    palette: Res<SocMaterials>,
+    mut satellites: Query<(&PowerSystem, &mut MeshMaterial3d<StandardMaterial>)>,
+) {
+    for (power, mut material) in &mut satellites {
+        let handle = match config.display.color_mode {
+            ColorMode::Default => manager.material(),
+            ColorMode::StateOfCharge => {
+                let i = (power.state_of_charge() * (SOC_COLORS - 1) as f64).round() as usize;
+                &palette.0[i.min(SOC_COLORS - 1)]
+            }
+        };
+        if material.0 != *handle {
+            material.0 = handle.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_step() {
+        let config = PowerConfig {
+            battery_capacity: 1000.0,
+            initial_soc: 0.25,
+            shed_soc: 0.2,
+            restore_soc: 0.3,
+            ..default()
+        };
+        let mut power = PowerSystem::new(&config);
+        power.consumption = 100.0;
+
+        // in eclipse, 100 Wh an hour: 250 Wh -> 200 Wh is still above shed_soc
+        assert!(!power.step(0.0, 1800.0, false, &config));
+        assert!((power.charge - 200.0).abs() < 1e-9);
+        assert!(power.step(0.0, 36.0, false, &config));
+        assert!((power.state_of_charge() - 0.199).abs() < 1e-9);
+
+        // in sunlight, 100 Wh an hour net: low until restore_soc, not just shed_soc
+        assert!(power.step(200.0, 360.0, true, &config));
+        assert!(power.state_of_charge() > config.shed_soc);
+        assert!(power.step(200.0, 3600.0 * 0.89, true, &config));
+        assert!(power.state_of_charge() < config.restore_soc);
+        assert!(!power.step(200.0, 72.0, true, &config));
+        assert!(power.state_of_charge() >= config.restore_soc);
+
+        // clamped to the battery
+        assert!(!power.step(200.0, 3600.0 * 100.0, false, &config));
+        assert_eq!(power.charge, config.battery_capacity);
+        assert!(power.step(0.0, 3600.0 * 100.0, false, &config));
+        assert_eq!(power.charge, 0.0);
+    }
+}
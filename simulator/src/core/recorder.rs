@@ -0,0 +1,509 @@
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use super::{link_budget::LinkBudget, network::SPEED_OF_LIGHT, *};
+
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(
+                FixedPostUpdate,
+                record
+                    .after(rebuild_network_graph)
+                    .run_if(resource_exists::<Recorder>),
+            )
+            .add_systems(Last, finish_on_exit.run_if(resource_exists::<Recorder>));
+    }
+}
+
+/// Writes positions, link events and link metrics to disk, in every configured format.
+#[derive(Resource)]
+struct Recorder {
+    interval: f64,
+    next_sample: f64, // 下次采样的仿真时间(s)
+    time: f64,        // 上次记录的仿真时间(s)
+    positions: Table,
+    link_events: Table,
+    link_metrics: Table,
+}
+
+/// A value of a row, in a column of the same type.
+enum Value<'a> {
+    Float(f64),
+    Text(&'a str),
+}
+
+/// Rows buffered between writes.
+enum Column {
+    Float(Vec<f64>),
+    Text(Vec<String>),
+}
+
+/// One time series, written to a `.csv` and/or an `.arrow` file.
+struct Table {
+    schema: SchemaRef,
+    columns: Vec<Column>,
+    csv: Option<BufWriter<File>>,
+    arrow: Option<FileWriter<BufWriter<File>>>,
+}
+
+impl Table {
+    /// Create the files of a table, with `header` as `#` comments of the CSV file and as the
+    /// schema metadata of the Arrow file.
+    fn create(
+        directory: &Path,
+        name: &str,
+        fields: &[(&str, DataType)],
+        header: &[(&str, String)],
+        formats: &[RecordFormat],
+    ) -> Result<Self> {
+        let metadata = header
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        let schema = Arc::new(
+            Schema::new(
+                fields
+                    .iter()
+                    .map(|(name, data_type)| Field::new(*name, data_type.clone(), false))
+                    .collect::<Vec<_>>(),
+            )
+            .with_metadata(metadata),
+        );
+        let columns = fields
+            .iter()
+            .map(|(_, data_type)| match data_type {
+                DataType::Utf8 => Column::Text(vec![]),
+                _ => Column::Float(vec![]),
+            })
+            .collect();
+
+        let mut csv = None;
+        let mut arrow = None;
+        for format in formats {
+            match format {
+                RecordFormat::Csv => {
+                    let mut file =
+                        BufWriter::new(File::create(directory.join(format!("{}.csv", name)))?);
+                    for (key, value) in header {
+                        if value.contains('\n') {
+                            writeln!(file, "# {}:", key)?;
+                            for line in value.lines() {
+                                writeln!(file, "#   {}", line)?;
+                            }
+                        } else {
+                            writeln!(file, "# {}: {}", key, value)?;
+                        }
+                    }
+                    let names: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+                    writeln!(file, "{}", names.join(","))?;
+                    csv = Some(file);
+                }
+                RecordFormat::Arrow => {
+                    let file =
+                        BufWriter::new(File::create(directory.join(format!("{}.arrow", name)))?);
+                    arrow = Some(FileWriter::try_new(file, &schema)?);
+                }
+            }
+        }
+        Ok(Self {
+            schema,
+            columns,
+            csv,
+            arrow,
+        })
+    }
+
+    fn push(&mut self, row: &[Value]) {
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            match (column, value) {
+                (Column::Float(values), Value::Float(value)) => values.push(*value),
+                (Column::Text(values), Value::Text(value)) => values.push(value.to_string()),
+                _ => unreachable!("value of the wrong type"),
+            }
+        }
+    }
+
+    /// Write the buffered rows.
+    fn flush(&mut self) -> Result {
+        let rows = match self.columns.first() {
+            Some(Column::Float(values)) => values.len(),
+            Some(Column::Text(values)) => values.len(),
+            None => 0,
+        };
+        if rows == 0 {
+            return Ok(());
+        }
+
+        if let Some(file) = &mut self.csv {
+            for row in 0..rows {
+                let line: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| match column {
+                        Column::Float(values) => values[row].to_string(),
+                        Column::Text(values) => csv_field(&values[row]),
+                    })
+                    .collect();
+                writeln!(file, "{}", line.join(","))?;
+            }
+            file.flush()?;
+        }
+        if let Some(writer) = &mut self.arrow {
+            let arrays = self
+                .columns
+                .iter()
+                .map(|column| match column {
+                    Column::Float(values) => {
+                        Arc::new(Float64Array::from(values.clone())) as ArrayRef
+                    }
+                    Column::Text(values) => Arc::new(StringArray::from(values.clone())),
+                })
+                .collect();
+            writer.write(&RecordBatch::try_new(self.schema.clone(), arrays)?)?;
+        }
+
+        for column in &mut self.columns {
+            match column {
+                Column::Float(values) => values.clear(),
+                Column::Text(values) => values.clear(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the buffered rows and close the files.
+    fn finish(&mut self) -> Result {
+        self.flush()?;
+        if let Some(mut writer) = self.arrow.take() {
+            // the footer of the Arrow file
+            writer.finish()?;
+        }
+        self.csv.take();
+        Ok(())
+    }
+}
+
+/// The serialized name of a unit enum variant.
+fn variant_name(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    config: Res<Config>,
+    clock: Res<SimulationClock>,
+    rng: Res<SimulationRng>,
+) -> Result {
+    let Some(recorder) = &config.recorder else {
+        return Ok(());
+    };
+    if recorder.interval <= 0.0 {
+        return Err("Recorder interval must be positive".into());
+    }
+    let directory = Path::new(&recorder.directory);
+    fs::create_dir_all(directory)?;
+    info!("Recording to {:?}", directory);
+
+    let header = [
+        (
+            "dataset",
+            config
+                .dataset
+                .as_ref()
+                .map_or("", |dataset| dataset.constellation_file())
+                .to_string(),
+        ),
+        ("seed", rng.seed().to_string()),
+        (
+            "epoch",
+            clock.epoch().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        ),
+        ("config", config.source.clone()),
+    ];
+    let table = |name, fields: &[(&str, DataType)]| {
+        Table::create(directory, name, fields, &header, &recorder.formats)
+    };
+
+    commands.insert_resource(Recorder {
+        interval: recorder.interval,
+        next_sample: clock.elapsed_secs(),
+        time: clock.elapsed_secs(),
+        positions: table(
+            "positions",
+            &[
+                ("time_s", DataType::Float64),
+                ("id", DataType::Utf8),
+                ("x_km", DataType::Float64),
+                ("y_km", DataType::Float64),
+                ("z_km", DataType::Float64),
+                ("latitude_deg", DataType::Float64),
+                ("longitude_deg", DataType::Float64),
+                ("altitude_km", DataType::Float64),
+            ],
+        )?,
+        link_events: table(
+            "link_events",
+            &[
+                ("time_s", DataType::Float64),
+                ("event", DataType::Utf8),
+                ("a", DataType::Utf8),
+                ("b", DataType::Utf8),
+                ("kind", DataType::Utf8),
+                ("reason", DataType::Utf8),
+                ("lifetime_s", DataType::Float64),
+            ],
+        )?,
+        link_metrics: table(
+            "links",
+            &[
+                ("time_s", DataType::Float64),
+                ("a", DataType::Utf8),
+                ("b", DataType::Utf8),
+                ("kind", DataType::Utf8),
+                ("distance_km", DataType::Float64),
+                ("delay_s", DataType::Float64),
+                ("snr_db", DataType::Float64),
+                ("capacity_bps", DataType::Float64),
+            ],
+        )?,
+    });
+    Ok(())
+}
+
+/// Record link events at every fixed step, and positions and link metrics at every sample.
+#[allow(clippy::too_many_arguments)]
+fn record(
+    clock: Res<SimulationClock>,
+    config: Res<Config>,
+    graph: Res<NetworkGraph>,
+    mut recorder: ResMut<Recorder>,
+    mut link_events: EventReader<LinkEvent>,
+    satellites: Query<(&Name, &Transform), With<Satellite>>,
+    names: Query<&Name>,
+    ground_stations: Query<(), With<GroundStation>>,
+) -> Result {
+    let now = clock.elapsed_secs();
+    if now < recorder.time {
+        // the clock jumped backwards, sample again from there
+        recorder.next_sample = now;
+    }
+    recorder.time = now;
+
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or_else(|_| format!("{}", entity), |name| name.to_string())
+    };
+    for event in link_events.read() {
+        let state = if event.is_up() { "up" } else { "down" };
+        let reason = event.reason.map(|reason| variant_name(&reason));
+        recorder.link_events.push(&[
+            Value::Float(event.time),
+            Value::Text(state),
+            Value::Text(&event.a),
+            Value::Text(&event.b),
+            Value::Text(&variant_name(&event.kind)),
+            Value::Text(reason.as_deref().unwrap_or_default()),
+            Value::Float(event.lifetime.unwrap_or(f64::NAN)),
+        ]);
+    }
+
+    if now < recorder.next_sample {
+        return Ok(());
+    }
+    recorder.next_sample = ((now / recorder.interval).floor() + 1.0) * recorder.interval;
+
+    for (name, transform) in &satellites {
+        let position = transform.translation;
+        let geodetic = transform_to_geodetic(transform, &clock);
+        recorder.positions.push(&[
+            Value::Float(now),
+            Value::Text(name),
+            Value::Float(position.x as f64),
+            Value::Float(position.y as f64),
+            Value::Float(position.z as f64),
+            Value::Float(geodetic.latitude.to_degrees()),
+            Value::Float(geodetic.longitude.to_degrees()),
+            Value::Float(geodetic.altitude),
+        ]);
+    }
+
+    let mut links: Vec<_> = graph
+        .links()
+        .map(|(a, b, delay)| {
+            let kind = if ground_stations.contains(a) || ground_stations.contains(b) {
+                "ground"
+            } else {
+                "isl"
+            };
+            (name(a), name(b), kind, delay)
+        })
+        .collect();
+    // deterministic order of the rows
+    links.sort_unstable_by(|x, y| (&x.0, &x.1).cmp(&(&y.0, &y.1)));
+    for (a, b, kind, delay) in links {
+        let budget = LinkBudget::new(&config.link_budget, delay * SPEED_OF_LIGHT);
+        recorder.link_metrics.push(&[
+            Value::Float(now),
+            Value::Text(&a),
+            Value::Text(&b),
+            Value::Text(kind),
+            Value::Float(budget.distance),
+            Value::Float(budget.delay),
+            Value::Float(budget.snr),
+            Value::Float(budget.capacity),
+        ]);
+    }
+
+    recorder.positions.flush()?;
+    recorder.link_events.flush()?;
+    recorder.link_metrics.flush()?;
+    Ok(())
+}
+
+/// Close the files, so the Arrow files get their footers.
+fn finish_on_exit(mut exit: EventReader<AppExit>, mut recorder: ResMut<Recorder>) -> Result {
+    if exit.read().next().is_none() {
+        return Ok(());
+    }
+    recorder.positions.finish()?;
+    recorder.link_events.finish()?;
+    recorder.link_metrics.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test_app, *};
+    use arrow_ipc::reader::FileReader;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("2019-074B"), "2019-074B");
+        assert_eq!(csv_field("STARLINK-1007, A"), "\"STARLINK-1007, A\"");
+        assert_eq!(csv_field("a \"b\""), "\"a \"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_record() {
+        let directory = std::env::temp_dir().join(format!("recorder_test_{}", std::process::id()));
+        let config = format!(
+            r#"
+            [Network]
+            port = 0
+            [Display]
+            orbit = false
+            connection = false
+            [Simulation]
+            start_time = "2025-05-01T00:00:00"
+            time_speed = 60.0
+            connection_distance = 5000.0
+            connection_number = 4
+            seed = 42
+            [Recorder]
+            directory = "{}"
+            interval = 10.0
+            formats = ["csv", "arrow"]
+            "#,
+            directory.display()
+        );
+        let semi_major_axis: f32 = EARTH_RADIUS + 550.0;
+        let mean_motion = (FACTOR.powi(3) / semi_major_axis.powi(3)).sqrt();
+
+        let mut app = test_app(&config, 1);
+        // the first update only starts the clocks
+        app.update();
+        app.world_mut().send_event(SpawnSatellites {
+            satellites: (0..3)
+                .map(|i| {
+                    let orbit = [
+                        mean_motion,
+                        0.0,
+                        53f32.to_radians(),
+                        0.0,
+                        0.0,
+                        0.1 * i as f32,
+                    ];
+                    (
+                        format!("S{}", i),
+                        OrbitalElements::from_slice(&orbit).unwrap(),
+                    )
+                })
+                .collect(),
+        });
+        for _ in 0..50 {
+            app.update();
+        }
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+
+        let csv = BufReader::new(File::open(directory.join("positions.csv")).unwrap());
+        let lines: Vec<String> = csv.lines().map(Result::unwrap).collect();
+        assert_eq!(lines[0], "# dataset: ");
+        assert_eq!(lines[1], "# seed: 42");
+        assert_eq!(lines[2], "# epoch: 2025-05-01T00:00:00.000Z");
+        assert_eq!(lines[3], "# config: ");
+        assert_eq!(
+            lines[4],
+            "time_s,id,x_km,y_km,z_km,latitude_deg,longitude_deg,altitude_km"
+        );
+        assert!(lines.len() > 5);
+
+        // the footer is written on exit, or the file could not be read
+        let arrow =
+            FileReader::try_new(File::open(directory.join("positions.arrow")).unwrap(), None)
+                .unwrap();
+        let schema = arrow.schema();
+        assert_eq!(schema.metadata()["seed"], "42");
+        assert_eq!(schema.metadata()["epoch"], "2025-05-01T00:00:00.000Z");
+        let names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "time_s",
+                "id",
+                "x_km",
+                "y_km",
+                "z_km",
+                "latitude_deg",
+                "longitude_deg",
+                "altitude_km"
+            ]
+        );
+        let rows: usize = arrow.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, lines.len() - 5);
+        assert!(
+            FileReader::try_new(File::open(directory.join("links.arrow")).unwrap(), None).is_ok()
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
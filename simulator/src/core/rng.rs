@@ -0,0 +1,36 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::*;
+
+/// The random number generator for every stochastic system.
+/// Seeded by `seed` in `[Simulation]`, so runs with the same seed, dataset and start time
+/// are identical.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SimulationRng {
+    #[deref]
+    rng: ChaCha8Rng,
+    seed: u64,
+}
+
+impl FromWorld for SimulationRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world
+            .resource::<Config>()
+            .simulation
+            .seed
+            .unwrap_or_else(rand::random);
+        info!("Random seed: {}", seed);
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            seed,
+        }
+    }
+}
+
+impl SimulationRng {
+    /// The seed in use, given or random.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::platform::collections::{HashMap, HashSet};
+
+use super::*;
+
+pub struct RoutingPlugin;
+
+impl Plugin for RoutingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Routing>()
+            .add_systems(FixedPostUpdate, update_routing.after(rebuild_network_graph));
+    }
+}
+
+/// A routing algorithm over the network graph.
+/// Implement it and call `Routing::set_algorithm` to use a custom one.
+pub trait RoutingAlgorithm: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Paths from `from` to `to`, the best first. Empty if they are not connected.
+    fn find_paths(&self, graph: &NetworkGraph, from: Entity, to: Entity) -> Vec<Path>;
+}
+
+/// Dijkstra by hop count.
+pub struct HopCount;
+
+impl RoutingAlgorithm for HopCount {
+    fn name(&self) -> String {
+        "hop_count".to_string()
+    }
+
+    fn find_paths(&self, graph: &NetworkGraph, from: Entity, to: Entity) -> Vec<Path> {
+        graph
+            .shortest_path_by(from, to, |_, _, _| Some(1.0))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Dijkstra by propagation delay.
+pub struct Latency;
+
+impl RoutingAlgorithm for Latency {
+    fn name(&self) -> String {
+        "latency".to_string()
+    }
+
+    fn find_paths(&self, graph: &NetworkGraph, from: Entity, to: Entity) -> Vec<Path> {
+        graph.shortest_path(from, to).into_iter().collect()
+    }
+}
+
+/// The `k` loopless paths with the least propagation delay, by Yen's algorithm.
+pub struct KShortestPaths {
+    pub k: usize,
+}
+
+impl RoutingAlgorithm for KShortestPaths {
+    fn name(&self) -> String {
+        format!("k_shortest_paths(k={})", self.k)
+    }
+
+    fn find_paths(&self, graph: &NetworkGraph, from: Entity, to: Entity) -> Vec<Path> {
+        let Some(first) = graph.shortest_path(from, to) else {
+            return vec![];
+        };
+        let mut paths = vec![first];
+        let mut candidates: Vec<Path> = vec![];
+
+        while paths.len() < self.k {
+            let last = &paths[paths.len() - 1];
+            for i in 0..last.nodes.len() - 1 {
+                let spur = last.nodes[i];
+                let root = &last.nodes[..=i];
+                // links leaving the spur node taken by found paths with the same root
+                let removed_links: HashSet<(Entity, Entity)> = paths
+                    .iter()
+                    .filter(|path| path.nodes.len() > i + 1 && path.nodes[..=i] == *root)
+                    .map(|path| (path.nodes[i], path.nodes[i + 1]))
+                    .collect();
+                // keep the path loopless
+                let removed_nodes: HashSet<Entity> = root[..i].iter().copied().collect();
+
+                let Some(spur_path) = graph.shortest_path_by(spur, to, |a, b, delay| {
+                    (!removed_links.contains(&(a, b)) && !removed_nodes.contains(&b))
+                        .then_some(delay)
+                }) else {
+                    continue;
+                };
+                let nodes: Vec<Entity> = root[..i].iter().copied().chain(spur_path.nodes).collect();
+                if paths
+                    .iter()
+                    .chain(&candidates)
+                    .any(|path| path.nodes == nodes)
+                {
+                    continue;
+                }
+                candidates.push(Path {
+                    delay: graph.path_delay(&nodes),
+                    nodes,
+                });
+            }
+
+            let Some(best) = candidates
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.delay.total_cmp(&b.1.delay))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            paths.push(candidates.remove(best));
+        }
+        paths
+    }
+}
+
+/// Build a built-in routing algorithm from the config.
+fn algorithm_from_config(kind: RoutingAlgorithmKind, k: usize) -> Box<dyn RoutingAlgorithm> {
+    match kind {
+        RoutingAlgorithmKind::HopCount => Box::new(HopCount),
+        RoutingAlgorithmKind::Latency => Box::new(Latency),
+        RoutingAlgorithmKind::KShortestPaths => Box::new(KShortestPaths { k: k.max(1) }),
+    }
+}
+
+/// Routing tables, computed from a snapshot of the `NetworkGraph` taken every `interval`
+/// seconds of simulation time. Routes are computed on the first lookup after each snapshot.
+#[derive(Resource)]
+pub struct Routing {
+    algorithm: Box<dyn RoutingAlgorithm>,
+    interval: f64,            // 更新间隔(s)
+    last_update: Option<f64>, // 上次更新时的仿真时间(s)
+    graph: NetworkGraph,
+    table: HashMap<(Entity, Entity), Vec<Path>>,
+}
+
+impl FromWorld for Routing {
+    fn from_world(world: &mut World) -> Self {
+        let config = &world.resource::<Config>().routing;
+        Self {
+            algorithm: algorithm_from_config(config.algorithm, config.k),
+            interval: config.interval,
+            last_update: None,
+            graph: default(),
+            table: default(),
+        }
+    }
+}
+
+impl Routing {
+    pub fn algorithm(&self) -> String {
+        self.algorithm.name()
+    }
+
+    /// Switch the algorithm, routes are recomputed on the next lookup.
+    pub fn set_algorithm(&mut self, algorithm: Box<dyn RoutingAlgorithm>) {
+        info!("Routing algorithm: {}", algorithm.name());
+        self.algorithm = algorithm;
+        self.table.clear();
+    }
+
+    pub fn set_algorithm_from_config(&mut self, kind: RoutingAlgorithmKind, k: usize) {
+        self.set_algorithm(algorithm_from_config(kind, k));
+    }
+
+    pub fn set_interval(&mut self, interval: f64) {
+        self.interval = interval.max(0.0);
+    }
+
+    /// Paths from `from` to `to` in the current routing table, the best first.
+    pub fn route(&mut self, from: Entity, to: Entity) -> &[Path] {
+        let Self {
+            algorithm,
+            graph,
+            table,
+            ..
+        } = self;
+        table
+            .entry((from, to))
+            .or_insert_with(|| algorithm.find_paths(graph, from, to))
+    }
+}
+
+/// Take a new snapshot of the network every `interval`.
+pub(super) fn update_routing(
+    clock: Res<SimulationClock>,
+    network: Res<NetworkGraph>,
+    mut routing: ResMut<Routing>,
+) {
+    let elapsed = clock.elapsed_secs();
+    // the clock may jump backwards
+    if routing
+        .last_update
+        .is_some_and(|last| (elapsed - last).abs() < routing.interval)
+    {
+        return;
+    }
+    routing.graph = network.clone();
+    routing.table.clear();
+    routing.last_update = Some(elapsed);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let [a, b, c, d] = [0, 1, 2, 3].map(Entity::from_raw);
+        let mut graph = NetworkGraph::default();
+        graph.link(a, b, 0.001);
+        graph.link(b, d, 0.001);
+        graph.link(a, c, 0.002);
+        graph.link(c, d, 0.0025);
+        graph.link(b, c, 0.0005);
+
+        let paths = KShortestPaths { k: 3 }.find_paths(&graph, a, d);
+        let nodes: Vec<_> = paths.iter().map(|path| path.nodes.clone()).collect();
+        assert_eq!(
+            nodes,
+            vec![vec![a, b, d], vec![a, c, b, d], vec![a, b, c, d]]
+        );
+
+        let hops = HopCount.find_paths(&graph, a, d);
+        assert_eq!(hops[0].nodes.len(), 3);
+    }
+}
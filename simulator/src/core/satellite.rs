@@ -1,3 +1,5 @@
+use std::f64::consts::TAU;
+
 use super::*;
 
 pub struct SatellitePlugin;
@@ -11,11 +13,15 @@ impl Plugin for SatellitePlugin {
 }
 
 #[derive(Component, Clone)]
-#[require(Transform)]
+#[require(Transform, Velocity, Connections)]
 pub struct Satellite {
     pub mean_anomaly: f32, // 平近点角(rad)
 }
 
+/// Velocity of a satellite in the scene frame (ECI).
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct Velocity(pub Vec3); // 速度(km/s)
+
 #[derive(Component, Debug)]
 #[relationship(relationship_target= FollowedBy)]
 pub struct Following(Entity);
@@ -40,39 +46,67 @@ pub fn create_satellite(
     )
 }
 
+/// Mean anomaly at the clock epoch, from which the current one is computed analytically.
+#[derive(Component)]
+struct EpochMeanAnomaly(f64);
+
+/// Set the mean anomaly of each satellite at the current clock time.
+/// New satellites are anchored to the clock epoch first.
 fn update_mean_anomaly(
+    mut commands: Commands,
     config: Res<Config>,
+    clock: Res<SimulationClock>,
     orbits: Query<(&Orbit, &FollowedBy)>,
-    mut satellites: Query<&mut Satellite>,
-    time: Res<Time<Fixed>>,
+    mut satellites: Query<(&mut Satellite, Option<&EpochMeanAnomaly>), Without<Sgp4Propagator>>,
 ) -> Result {
+    let elapsed = clock.elapsed_secs();
     for (orbit, sates) in orbits {
-        let mean_motion = orbit.mean_motion;
+        let mean_motion = if config.simulation.j2 {
+            orbit.mean_motion + orbit.j2_rates().mean_anomaly
+        } else {
+            orbit.mean_motion
+        } as f64;
         for sate in &sates.0 {
-            let mut sate = satellites.get_mut(*sate)?;
-            sate.mean_anomaly += mean_motion * time.delta_secs() * config.simulation.time_speed;
-            sate.mean_anomaly %= 2. * PI;
+            // satellites driven by SGP4 are skipped
+            let Ok((mut satellite, epoch_mean_anomaly)) = satellites.get_mut(*sate) else {
+                continue;
+            };
+            match epoch_mean_anomaly {
+                Some(EpochMeanAnomaly(mean_anomaly)) => {
+                    satellite.mean_anomaly =
+                        (mean_anomaly + mean_motion * elapsed).rem_euclid(TAU) as f32;
+                }
+                None => {
+                    let mean_anomaly = satellite.mean_anomaly as f64 - mean_motion * elapsed;
+                    commands
+                        .entity(*sate)
+                        .insert(EpochMeanAnomaly(mean_anomaly));
+                }
+            }
         }
     }
 
     Ok(())
 }
 
-fn update_satellite_position(
+pub(super) fn update_satellite_position(
     orbits: Query<(&Orbit, &FollowedBy)>,
-    mut satellites: Query<(&mut Transform, &Satellite)>,
+    mut satellites: Query<(&mut Transform, &mut Velocity, &Satellite), Without<Sgp4Propagator>>,
 ) -> Result {
     for (orbit, sates) in orbits {
         for sate in &sates.0 {
-            let (mut transform, sate) = satellites.get_mut(*sate)?;
+            let Ok((mut transform, mut velocity, sate)) = satellites.get_mut(*sate) else {
+                continue;
+            };
             transform.translation = get_pos_from_elements(orbit, sate.mean_anomaly);
+            velocity.0 = get_vel_from_elements(orbit, sate.mean_anomaly);
         }
     }
 
     Ok(())
 }
 
-fn get_pos_from_elements(orbital: &Orbit, mean_anomaly: f32) -> Vec3 {
+pub(super) fn get_pos_from_elements(orbital: &Orbit, mean_anomaly: f32) -> Vec3 {
     let true_anomaly = anomaly_mean_to_true(mean_anomaly, orbital.eccentricity).unwrap();
     let n = orbital.mean_motion.powf(-2. / 3.);
     let semi_major_axis = FACTOR * n;
@@ -93,3 +127,48 @@ fn get_pos_from_elements(orbital: &Orbit, mean_anomaly: f32) -> Vec3 {
 
     rot * local_location // apply rotation
 }
+
+/// Two-body velocity (km/s) from the orbital elements.
+pub(super) fn get_vel_from_elements(orbital: &Orbit, mean_anomaly: f32) -> Vec3 {
+    let true_anomaly = anomaly_mean_to_true(mean_anomaly, orbital.eccentricity).unwrap();
+    let semi_major_axis = FACTOR * orbital.mean_motion.powf(-2. / 3.);
+    // v = sqrt(u / p) * (-sin(true_anomaly), e + cos(true_anomaly)) on the orbital plane
+    let semi_latus_rectum = semi_major_axis * (1.0 - orbital.eccentricity.powi(2));
+    let speed = (FACTOR.powi(3) / semi_latus_rectum).sqrt();
+    let local_velocity = Vec3::new(
+        -speed * true_anomaly.sin(),
+        speed * (orbital.eccentricity + true_anomaly.cos()),
+        0.0,
+    );
+
+    let rot = get_rotated_quat(
+        orbital.inclination,
+        orbital.longitude_of_ascending_node,
+        orbital.argument_of_periapsis,
+    );
+
+    rot * local_velocity
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_velocity() {
+        let orbit = Orbit {
+            mean_motion: 15.0 * 2.0 * PI / 86400.0,
+            eccentricity: 0.1,
+            inclination: 0.9,
+            longitude_of_ascending_node: 1.0,
+            argument_of_periapsis: 0.5,
+        };
+        // the derivative of the position
+        let dt = 0.5;
+        let mean_anomaly = 1.2;
+        let before = get_pos_from_elements(&orbit, mean_anomaly - orbit.mean_motion * dt);
+        let after = get_pos_from_elements(&orbit, mean_anomaly + orbit.mean_motion * dt);
+        let velocity = get_vel_from_elements(&orbit, mean_anomaly);
+        assert!((velocity - (after - before) / (2.0 * dt)).length() < 1e-2);
+    }
+}
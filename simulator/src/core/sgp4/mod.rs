@@ -0,0 +1,115 @@
+use bevy::ecs::relationship::Relationship;
+use chrono::TimeZone;
+use std::f64::consts::TAU;
+
+use super::*;
+
+mod model;
+
+use model::{Sgp4, Sgp4Elements};
+
+pub struct Sgp4Plugin;
+
+impl Plugin for Sgp4Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, propagate_sgp4);
+    }
+}
+
+/// Drives a satellite (and its orbit) by SGP4/SDP4 instead of two-body motion.
+/// Only available for satellites imported with a full OMM record.
+#[derive(Component, Debug, Clone)]
+pub struct Sgp4Propagator {
+    model: Sgp4,
+    minutes_at_clock_epoch: f64, // minutes from the element set epoch to the clock epoch
+}
+
+impl Sgp4Propagator {
+    /// Create a propagator from raw satellite data, relative to the clock epoch.
+    pub fn from_raw_sate_data(
+        raw_data: &RawSatelliteData,
+        clock_epoch: DateTime<Utc>,
+    ) -> Result<Self, String> {
+        let epoch = parse_time_from_str(&raw_data.epoch)?;
+        let model = Sgp4::new(&Sgp4Elements {
+            epoch: days_since_1950(epoch),
+            bstar: raw_data.bstar,
+            eccentricity: raw_data.eccentricity,
+            argument_of_perigee: raw_data.arg_of_pericenter.to_radians(),
+            inclination: raw_data.inclination.to_radians(),
+            mean_anomaly: raw_data.mean_anomaly.to_radians(),
+            mean_motion: raw_data.mean_motion * TAU / 1440.0, // rev/day to rad/min
+            raan: raw_data.ra_of_asc_node.to_radians(),
+        })
+        .map_err(|err| format!("{}: {}", raw_data.object_id, err))?;
+
+        let duration = clock_epoch - epoch;
+        Ok(Self {
+            model,
+            minutes_at_clock_epoch: duration.num_milliseconds() as f64 / 60_000.0,
+        })
+    }
+
+    /// "sdp4" for deep space element sets (a period of 225 minutes or more), "sgp4" otherwise.
+    pub fn name(&self) -> &'static str {
+        if self.model.is_deep_space() {
+            "sdp4"
+        } else {
+            "sgp4"
+        }
+    }
+}
+
+/// SGP4 epoch: days since 1949 December 31 00:00 UT.
+fn days_since_1950(time: DateTime<Utc>) -> f64 {
+    let base = Utc.with_ymd_and_hms(1949, 12, 31, 0, 0, 0).unwrap();
+    (time - base).num_milliseconds() as f64 / 86_400_000.0
+}
+
+/// Propagate SGP4 satellites, and write the mean elements back to `Satellite` and `Orbit`.
+pub(super) fn propagate_sgp4(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut satellites: Query<(
+        Entity,
+        &Name,
+        &Sgp4Propagator,
+        &mut Satellite,
+        &mut Transform,
+        &mut Velocity,
+        &Following,
+    )>,
+    mut orbits: Query<&mut Orbit>,
+) -> Result {
+    let elapsed_minutes = clock.elapsed_secs() / 60.0;
+
+    for (sat, name, propagator, mut satellite, mut transform, mut velocity, following) in
+        &mut satellites
+    {
+        let tsince = propagator.minutes_at_clock_epoch + elapsed_minutes;
+        let prediction = match propagator.model.propagate(tsince) {
+            Ok(prediction) => prediction,
+            Err(err) => {
+                warn!("SGP4 failed for {}, fall back to two-body: {}", name, err);
+                commands.entity(sat).remove::<Sgp4Propagator>();
+                continue;
+            }
+        };
+
+        let [x, y, z] = prediction.position;
+        transform.translation = Vec3::new(x as f32, y as f32, z as f32);
+        let [vx, vy, vz] = prediction.velocity;
+        velocity.0 = Vec3::new(vx as f32, vy as f32, vz as f32);
+
+        let mean = prediction.mean;
+        satellite.mean_anomaly = mean.mean_anomaly as f32;
+        let mut orbit = orbits.get_mut(following.get())?;
+        orbit.mean_motion = (mean.mean_motion / 60.0) as f32; // rad/min to rad/s
+        orbit.eccentricity = mean.eccentricity as f32;
+        orbit.inclination = mean.inclination as f32;
+        orbit.longitude_of_ascending_node = mean.raan as f32;
+        orbit.argument_of_periapsis = mean.argument_of_perigee as f32;
+    }
+
+    Ok(())
+}
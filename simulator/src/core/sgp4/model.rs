@@ -0,0 +1,1306 @@
+//! SGP4/SDP4 analytical propagator.
+//!
+//! Port of David Vallado's reference implementation (`SGP4.cpp`, "Revisiting Spacetrack
+//! Report #3", AIAA 2006-6753), using WGS-72 constants and the "improved" operation mode.
+//! All quantities are `f64`, times are in minutes and distances in km.
+
+use std::f64::consts::{PI, TAU};
+
+// WGS-72 constants
+const MU: f64 = 398600.8; // km^3/s^2
+const RADIUS_EARTH_KM: f64 = 6378.135;
+const J2: f64 = 0.001082616;
+const J3: f64 = -0.00000253881;
+const J4: f64 = -0.00000165597;
+const J3OJ2: f64 = J3 / J2;
+const X2O3: f64 = 2.0 / 3.0;
+const TEMP4: f64 = 1.5e-12;
+
+/// sqrt(mu) in earth radii^1.5 per minute.
+fn xke() -> f64 {
+    60.0 / (RADIUS_EARTH_KM.powi(3) / MU).sqrt()
+}
+
+/// Mean elements used to initialise the propagator.
+#[derive(Debug, Clone)]
+pub struct Sgp4Elements {
+    pub epoch: f64,               // days since 1949 December 31 00:00 UT
+    pub bstar: f64,               // drag term (1/earth radii)
+    pub eccentricity: f64,        // 离心率
+    pub argument_of_perigee: f64, // 近地点角距(rad)
+    pub inclination: f64,         // 轨道倾角(rad)
+    pub mean_anomaly: f64,        // 平近点角(rad)
+    pub mean_motion: f64,         // 平均运动, Kozai(rad/min)
+    pub raan: f64,                // 升交点赤经(rad)
+}
+
+/// Result of a propagation step.
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    pub position: [f64; 3], // TEME (km)
+    pub velocity: [f64; 3], // TEME (km/s)
+    pub mean: MeanElements,
+}
+
+/// Secularly updated mean elements at the propagation time.
+#[derive(Debug, Clone)]
+pub struct MeanElements {
+    pub mean_motion: f64, // rad/min
+    pub eccentricity: f64,
+    pub inclination: f64,         // rad
+    pub raan: f64,                // rad
+    pub argument_of_perigee: f64, // rad
+    pub mean_anomaly: f64,        // rad
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    NearEarth,
+    DeepSpace,
+}
+
+/// Lunar-solar terms, only populated for deep space orbits.
+#[derive(Debug, Clone, Default)]
+struct DeepSpace {
+    e3: f64,
+    ee2: f64,
+    se2: f64,
+    se3: f64,
+    sgh2: f64,
+    sgh3: f64,
+    sgh4: f64,
+    sh2: f64,
+    sh3: f64,
+    si2: f64,
+    si3: f64,
+    sl2: f64,
+    sl3: f64,
+    sl4: f64,
+    xgh2: f64,
+    xgh3: f64,
+    xgh4: f64,
+    xh2: f64,
+    xh3: f64,
+    xi2: f64,
+    xi3: f64,
+    xl2: f64,
+    xl3: f64,
+    xl4: f64,
+    zmol: f64,
+    zmos: f64,
+    // resonance
+    irez: u8,
+    d2201: f64,
+    d2211: f64,
+    d3210: f64,
+    d3222: f64,
+    d4410: f64,
+    d4422: f64,
+    d5220: f64,
+    d5232: f64,
+    d5421: f64,
+    d5433: f64,
+    dedt: f64,
+    didt: f64,
+    dmdt: f64,
+    dnodt: f64,
+    domdt: f64,
+    del1: f64,
+    del2: f64,
+    del3: f64,
+    xfact: f64,
+    xlamo: f64,
+}
+
+/// Initialised SGP4/SDP4 propagator for one element set.
+#[derive(Debug, Clone)]
+pub struct Sgp4 {
+    method: Method,
+    isimp: bool,
+    // elements
+    bstar: f64,
+    ecco: f64,
+    argpo: f64,
+    inclo: f64,
+    mo: f64,
+    nodeo: f64,
+    no_unkozai: f64,
+    gsto: f64,
+    // near earth
+    aycof: f64,
+    con41: f64,
+    cc1: f64,
+    cc4: f64,
+    cc5: f64,
+    d2: f64,
+    d3: f64,
+    d4: f64,
+    delmo: f64,
+    eta: f64,
+    argpdot: f64,
+    omgcof: f64,
+    sinmao: f64,
+    t2cof: f64,
+    t3cof: f64,
+    t4cof: f64,
+    t5cof: f64,
+    x1mth2: f64,
+    x7thm1: f64,
+    mdot: f64,
+    nodedot: f64,
+    xlcof: f64,
+    xmcof: f64,
+    nodecf: f64,
+    deep: DeepSpace,
+}
+
+impl Sgp4 {
+    /// Initialise the propagator (Vallado's `sgp4init`).
+    pub fn new(elements: &Sgp4Elements) -> Result<Self, String> {
+        let xke = xke();
+        let ecco = elements.eccentricity;
+        let inclo = elements.inclination;
+        let argpo = elements.argument_of_perigee;
+        let mo = elements.mean_anomaly;
+        let nodeo = elements.raan;
+        let bstar = elements.bstar;
+
+        if !(0.0..1.0).contains(&ecco) {
+            return Err(format!("Invalid eccentricity: {}", ecco));
+        }
+        if elements.mean_motion <= 0.0 {
+            return Err(format!("Invalid mean motion: {}", elements.mean_motion));
+        }
+
+        let ss = 78.0 / RADIUS_EARTH_KM + 1.0;
+        let qzms2t = ((120.0 - 78.0) / RADIUS_EARTH_KM).powi(4);
+
+        // ---- initl ----
+        let eccsq = ecco * ecco;
+        let omeosq = 1.0 - eccsq;
+        let rteosq = omeosq.sqrt();
+        let cosio = inclo.cos();
+        let cosio2 = cosio * cosio;
+        let ak = (xke / elements.mean_motion).powf(X2O3);
+        let d1 = 0.75 * J2 * (3.0 * cosio2 - 1.0) / (rteosq * omeosq);
+        let mut del = d1 / (ak * ak);
+        let adel = ak * (1.0 - del * del - del * (1.0 / 3.0 + 134.0 * del * del / 81.0));
+        del = d1 / (adel * adel);
+        let no_unkozai = elements.mean_motion / (1.0 + del);
+        let ao = (xke / no_unkozai).powf(X2O3);
+        let sinio = inclo.sin();
+        let po = ao * omeosq;
+        let con42 = 1.0 - 5.0 * cosio2;
+        let con41 = -con42 - cosio2 - cosio2;
+        let posq = po * po;
+        let rp = ao * (1.0 - ecco);
+        let gsto = gstime(elements.epoch + 2433281.5);
+
+        // ---- sgp4init ----
+        let mut isimp = rp < 220.0 / RADIUS_EARTH_KM + 1.0;
+        let mut sfour = ss;
+        let mut qzms24 = qzms2t;
+        let perige = (rp - 1.0) * RADIUS_EARTH_KM;
+        if perige < 156.0 {
+            sfour = perige - 78.0;
+            if perige < 98.0 {
+                sfour = 20.0;
+            }
+            qzms24 = ((120.0 - sfour) / RADIUS_EARTH_KM).powi(4);
+            sfour = sfour / RADIUS_EARTH_KM + 1.0;
+        }
+        let pinvsq = 1.0 / posq;
+        let tsi = 1.0 / (ao - sfour);
+        let eta = ao * ecco * tsi;
+        let etasq = eta * eta;
+        let eeta = ecco * eta;
+        let psisq = (1.0 - etasq).abs();
+        let coef = qzms24 * tsi.powi(4);
+        let coef1 = coef / psisq.powf(3.5);
+        let cc2 = coef1
+            * no_unkozai
+            * (ao * (1.0 + 1.5 * etasq + eeta * (4.0 + etasq))
+                + 0.375 * J2 * tsi / psisq * con41 * (8.0 + 3.0 * etasq * (8.0 + etasq)));
+        let cc1 = bstar * cc2;
+        let cc3 = if ecco > 1.0e-4 {
+            -2.0 * coef * tsi * J3OJ2 * no_unkozai * sinio / ecco
+        } else {
+            0.0
+        };
+        let x1mth2 = 1.0 - cosio2;
+        let cc4 = 2.0
+            * no_unkozai
+            * coef1
+            * ao
+            * omeosq
+            * (eta * (2.0 + 0.5 * etasq) + ecco * (0.5 + 2.0 * etasq)
+                - J2 * tsi / (ao * psisq)
+                    * (-3.0 * con41 * (1.0 - 2.0 * eeta + etasq * (1.5 - 0.5 * eeta))
+                        + 0.75
+                            * x1mth2
+                            * (2.0 * etasq - eeta * (1.0 + etasq))
+                            * (2.0 * argpo).cos()));
+        let cc5 = 2.0 * coef1 * ao * omeosq * (1.0 + 2.75 * (etasq + eeta) + eeta * etasq);
+        let cosio4 = cosio2 * cosio2;
+        let temp1 = 1.5 * J2 * pinvsq * no_unkozai;
+        let temp2 = 0.5 * temp1 * J2 * pinvsq;
+        let temp3 = -0.46875 * J4 * pinvsq * pinvsq * no_unkozai;
+        let mdot = no_unkozai
+            + 0.5 * temp1 * rteosq * con41
+            + 0.0625 * temp2 * rteosq * (13.0 - 78.0 * cosio2 + 137.0 * cosio4);
+        let argpdot = -0.5 * temp1 * con42
+            + 0.0625 * temp2 * (7.0 - 114.0 * cosio2 + 395.0 * cosio4)
+            + temp3 * (3.0 - 36.0 * cosio2 + 49.0 * cosio4);
+        let xhdot1 = -temp1 * cosio;
+        let nodedot = xhdot1
+            + (0.5 * temp2 * (4.0 - 19.0 * cosio2) + 2.0 * temp3 * (3.0 - 7.0 * cosio2)) * cosio;
+        let xpidot = argpdot + nodedot;
+        let omgcof = bstar * cc3 * argpo.cos();
+        let xmcof = if ecco > 1.0e-4 {
+            -X2O3 * coef * bstar / eeta
+        } else {
+            0.0
+        };
+        let nodecf = 3.5 * omeosq * xhdot1 * cc1;
+        let t2cof = 1.5 * cc1;
+        let xlcof = if (cosio + 1.0).abs() > TEMP4 {
+            -0.25 * J3OJ2 * sinio * (3.0 + 5.0 * cosio) / (1.0 + cosio)
+        } else {
+            -0.25 * J3OJ2 * sinio * (3.0 + 5.0 * cosio) / TEMP4
+        };
+        let aycof = -0.5 * J3OJ2 * sinio;
+        let delmo = (1.0 + eta * mo.cos()).powi(3);
+        let sinmao = mo.sin();
+        let x7thm1 = 7.0 * cosio2 - 1.0;
+
+        let mut method = Method::NearEarth;
+        let mut deep = DeepSpace::default();
+        if TAU / no_unkozai >= 225.0 {
+            method = Method::DeepSpace;
+            isimp = true;
+            let com = dscom(elements.epoch, ecco, argpo, 0.0, inclo, nodeo, no_unkozai);
+            deep = dsinit(
+                &com,
+                DsinitInput {
+                    argpo,
+                    gsto,
+                    mo,
+                    mdot,
+                    no: no_unkozai,
+                    nodeo,
+                    nodedot,
+                    xpidot,
+                    ecco,
+                    eccsq,
+                },
+            );
+        }
+
+        let (mut d2, mut d3, mut d4, mut t3cof, mut t4cof, mut t5cof) =
+            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        if !isimp {
+            let cc1sq = cc1 * cc1;
+            d2 = 4.0 * ao * tsi * cc1sq;
+            let temp = d2 * tsi * cc1 / 3.0;
+            d3 = (17.0 * ao + sfour) * temp;
+            d4 = 0.5 * temp * ao * tsi * (221.0 * ao + 31.0 * sfour) * cc1;
+            t3cof = d2 + 2.0 * cc1sq;
+            t4cof = 0.25 * (3.0 * d3 + cc1 * (12.0 * d2 + 10.0 * cc1sq));
+            t5cof = 0.2
+                * (3.0 * d4 + 12.0 * cc1 * d3 + 6.0 * d2 * d2 + 15.0 * cc1sq * (2.0 * d2 + cc1sq));
+        }
+
+        let sgp4 = Self {
+            method,
+            isimp,
+            bstar,
+            ecco,
+            argpo,
+            inclo,
+            mo,
+            nodeo,
+            no_unkozai,
+            gsto,
+            aycof,
+            con41,
+            cc1,
+            cc4,
+            cc5,
+            d2,
+            d3,
+            d4,
+            delmo,
+            eta,
+            argpdot,
+            omgcof,
+            sinmao,
+            t2cof,
+            t3cof,
+            t4cof,
+            t5cof,
+            x1mth2,
+            x7thm1,
+            mdot,
+            nodedot,
+            xlcof,
+            xmcof,
+            nodecf,
+            deep,
+        };
+
+        // propagate to epoch to catch invalid element sets early
+        sgp4.propagate(0.0)?;
+        Ok(sgp4)
+    }
+
+    /// Whether the element set uses the deep space (SDP4) branch.
+    pub fn is_deep_space(&self) -> bool {
+        self.method == Method::DeepSpace
+    }
+
+    /// Propagate to `tsince` minutes after the element set epoch (Vallado's `sgp4`).
+    pub fn propagate(&self, tsince: f64) -> Result<Prediction, String> {
+        let xke = xke();
+        let vkmpersec = RADIUS_EARTH_KM * xke / 60.0;
+        let t = tsince;
+
+        // update for secular gravity and atmospheric drag
+        let xmdf = self.mo + self.mdot * t;
+        let argpdf = self.argpo + self.argpdot * t;
+        let nodedf = self.nodeo + self.nodedot * t;
+        let mut argpm = argpdf;
+        let mut mm = xmdf;
+        let t2 = t * t;
+        let mut nodem = nodedf + self.nodecf * t2;
+        let mut tempa = 1.0 - self.cc1 * t;
+        let mut tempe = self.bstar * self.cc4 * t;
+        let mut templ = self.t2cof * t2;
+
+        if !self.isimp {
+            let delomg = self.omgcof * t;
+            let delmtemp = 1.0 + self.eta * xmdf.cos();
+            let delm = self.xmcof * (delmtemp.powi(3) - self.delmo);
+            let temp = delomg + delm;
+            mm = xmdf + temp;
+            argpm = argpdf - temp;
+            let t3 = t2 * t;
+            let t4 = t3 * t;
+            tempa = tempa - self.d2 * t2 - self.d3 * t3 - self.d4 * t4;
+            tempe += self.bstar * self.cc5 * (mm.sin() - self.sinmao);
+            templ += self.t3cof * t3 + t4 * (self.t4cof + t * self.t5cof);
+        }
+
+        let mut nm = self.no_unkozai;
+        let mut em = self.ecco;
+        let mut inclm = self.inclo;
+        if self.method == Method::DeepSpace {
+            dspace(
+                &self.deep,
+                DspaceState {
+                    argpo: self.argpo,
+                    argpdot: self.argpdot,
+                    t,
+                    gsto: self.gsto,
+                    no: self.no_unkozai,
+                },
+                &mut em,
+                &mut argpm,
+                &mut inclm,
+                &mut mm,
+                &mut nodem,
+                &mut nm,
+            );
+        }
+
+        if nm <= 0.0 {
+            return Err(format!("Mean motion is less than zero: {}", nm));
+        }
+        let am = (xke / nm).powf(X2O3) * tempa * tempa;
+        nm = xke / am.powf(1.5);
+        em -= tempe;
+        if !(-0.001..1.0).contains(&em) {
+            return Err(format!("Mean eccentricity out of range: {}", em));
+        }
+        if em < 1.0e-6 {
+            em = 1.0e-6;
+        }
+        mm += self.no_unkozai * templ;
+        let mut xlm = mm + argpm + nodem;
+        nodem %= TAU;
+        argpm %= TAU;
+        xlm %= TAU;
+        mm = (xlm - argpm - nodem) % TAU;
+
+        let mean = MeanElements {
+            mean_motion: nm,
+            eccentricity: em,
+            inclination: inclm,
+            raan: nodem.rem_euclid(TAU),
+            argument_of_perigee: argpm.rem_euclid(TAU),
+            mean_anomaly: mm.rem_euclid(TAU),
+        };
+
+        // add lunar-solar periodics
+        let mut ep = em;
+        let mut xincp = inclm;
+        let mut argpp = argpm;
+        let mut nodep = nodem;
+        let mut mp = mm;
+        let mut sinip = inclm.sin();
+        let mut cosip = inclm.cos();
+        let mut aycof = self.aycof;
+        let mut xlcof = self.xlcof;
+        let mut con41 = self.con41;
+        let mut x1mth2 = self.x1mth2;
+        let mut x7thm1 = self.x7thm1;
+
+        if self.method == Method::DeepSpace {
+            dpper(
+                &self.deep, t, &mut ep, &mut xincp, &mut nodep, &mut argpp, &mut mp,
+            );
+            if xincp < 0.0 {
+                xincp = -xincp;
+                nodep += PI;
+                argpp -= PI;
+            }
+            if !(0.0..=1.0).contains(&ep) {
+                return Err(format!("Perturbed eccentricity out of range: {}", ep));
+            }
+
+            // long period periodics
+            sinip = xincp.sin();
+            cosip = xincp.cos();
+            aycof = -0.5 * J3OJ2 * sinip;
+            xlcof = if (cosip + 1.0).abs() > TEMP4 {
+                -0.25 * J3OJ2 * sinip * (3.0 + 5.0 * cosip) / (1.0 + cosip)
+            } else {
+                -0.25 * J3OJ2 * sinip * (3.0 + 5.0 * cosip) / TEMP4
+            };
+        }
+
+        let axnl = ep * argpp.cos();
+        let temp = 1.0 / (am * (1.0 - ep * ep));
+        let aynl = ep * argpp.sin() + temp * aycof;
+        let xl = mp + argpp + nodep + temp * xlcof * axnl;
+
+        // solve kepler's equation
+        let u = (xl - nodep) % TAU;
+        let mut eo1 = u;
+        let mut tem5: f64 = 9999.9;
+        let mut ktr = 1;
+        let (mut sineo1, mut coseo1) = (0.0, 0.0);
+        while tem5.abs() >= 1.0e-12 && ktr <= 10 {
+            sineo1 = eo1.sin();
+            coseo1 = eo1.cos();
+            tem5 = 1.0 - coseo1 * axnl - sineo1 * aynl;
+            tem5 = (u - aynl * coseo1 + axnl * sineo1 - eo1) / tem5;
+            if tem5.abs() >= 0.95 {
+                tem5 = 0.95_f64.copysign(tem5);
+            }
+            eo1 += tem5;
+            ktr += 1;
+        }
+
+        // short period preliminary quantities
+        let ecose = axnl * coseo1 + aynl * sineo1;
+        let esine = axnl * sineo1 - aynl * coseo1;
+        let el2 = axnl * axnl + aynl * aynl;
+        let pl = am * (1.0 - el2);
+        if pl < 0.0 {
+            return Err(format!("Semi-latus rectum is less than zero: {}", pl));
+        }
+        let rl = am * (1.0 - ecose);
+        let rdotl = am.sqrt() * esine / rl;
+        let rvdotl = pl.sqrt() / rl;
+        let betal = (1.0 - el2).sqrt();
+        let temp = esine / (1.0 + betal);
+        let sinu = am / rl * (sineo1 - aynl - axnl * temp);
+        let cosu = am / rl * (coseo1 - axnl + aynl * temp);
+        let mut su = sinu.atan2(cosu);
+        let sin2u = (cosu + cosu) * sinu;
+        let cos2u = 1.0 - 2.0 * sinu * sinu;
+        let temp = 1.0 / pl;
+        let temp1 = 0.5 * J2 * temp;
+        let temp2 = temp1 * temp;
+
+        // update for short period periodics
+        if self.method == Method::DeepSpace {
+            let cosisq = cosip * cosip;
+            con41 = 3.0 * cosisq - 1.0;
+            x1mth2 = 1.0 - cosisq;
+            x7thm1 = 7.0 * cosisq - 1.0;
+        }
+        let mrt = rl * (1.0 - 1.5 * temp2 * betal * con41) + 0.5 * temp1 * x1mth2 * cos2u;
+        su -= 0.25 * temp2 * x7thm1 * sin2u;
+        let xnode = nodep + 1.5 * temp2 * cosip * sin2u;
+        let xinc = xincp + 1.5 * temp2 * cosip * sinip * cos2u;
+        let mvt = rdotl - nm * temp1 * x1mth2 * sin2u / xke;
+        let rvdot = rvdotl + nm * temp1 * (x1mth2 * cos2u + 1.5 * con41) / xke;
+
+        // orientation vectors
+        let (sinsu, cossu) = su.sin_cos();
+        let (snod, cnod) = xnode.sin_cos();
+        let (sini, cosi) = xinc.sin_cos();
+        let xmx = -snod * cosi;
+        let xmy = cnod * cosi;
+        let ux = xmx * sinsu + cnod * cossu;
+        let uy = xmy * sinsu + snod * cossu;
+        let uz = sini * sinsu;
+        let vx = xmx * cossu - cnod * sinsu;
+        let vy = xmy * cossu - snod * sinsu;
+        let vz = sini * cossu;
+
+        if mrt < 1.0 {
+            return Err(format!("Satellite has decayed (r = {} er)", mrt));
+        }
+
+        Ok(Prediction {
+            position: [
+                mrt * ux * RADIUS_EARTH_KM,
+                mrt * uy * RADIUS_EARTH_KM,
+                mrt * uz * RADIUS_EARTH_KM,
+            ],
+            velocity: [
+                (mvt * ux + rvdot * vx) * vkmpersec,
+                (mvt * uy + rvdot * vy) * vkmpersec,
+                (mvt * uz + rvdot * vz) * vkmpersec,
+            ],
+            mean,
+        })
+    }
+}
+
+/// Greenwich sidereal time (rad) for a UT1 julian date.
+fn gstime(jdut1: f64) -> f64 {
+    let tut1 = (jdut1 - 2451545.0) / 36525.0;
+    let temp = -6.2e-6 * tut1 * tut1 * tut1
+        + 0.093104 * tut1 * tut1
+        + (876600.0 * 3600.0 + 8640184.812866) * tut1
+        + 67310.54841; // sec
+    (temp * PI / 180.0 / 240.0).rem_euclid(TAU)
+}
+
+/// Output of `dscom`, the deep space common terms.
+struct Dscom {
+    sinim: f64,
+    cosim: f64,
+    emsq: f64,
+    s1: f64,
+    s2: f64,
+    s3: f64,
+    s4: f64,
+    s5: f64,
+    ss1: f64,
+    ss2: f64,
+    ss3: f64,
+    ss4: f64,
+    ss5: f64,
+    sz1: f64,
+    sz3: f64,
+    sz11: f64,
+    sz13: f64,
+    sz21: f64,
+    sz23: f64,
+    sz31: f64,
+    sz33: f64,
+    z1: f64,
+    z3: f64,
+    z11: f64,
+    z13: f64,
+    z21: f64,
+    z23: f64,
+    z31: f64,
+    z33: f64,
+    em: f64,
+    nm: f64,
+    inclm: f64,
+    terms: DeepSpace,
+}
+
+fn dscom(epoch: f64, ep: f64, argpp: f64, tc: f64, inclp: f64, nodep: f64, np: f64) -> Dscom {
+    const ZES: f64 = 0.01675;
+    const ZEL: f64 = 0.05490;
+    const C1SS: f64 = 2.9864797e-6;
+    const C1L: f64 = 4.7968065e-7;
+    const ZSINIS: f64 = 0.39785416;
+    const ZCOSIS: f64 = 0.91744867;
+    const ZCOSGS: f64 = 0.1945905;
+    const ZSINGS: f64 = -0.98088458;
+
+    let nm = np;
+    let em = ep;
+    let snodm = nodep.sin();
+    let cnodm = nodep.cos();
+    let sinomm = argpp.sin();
+    let cosomm = argpp.cos();
+    let sinim = inclp.sin();
+    let cosim = inclp.cos();
+    let emsq = em * em;
+    let betasq = 1.0 - emsq;
+    let rtemsq = betasq.sqrt();
+
+    // initialize lunar solar terms
+    let day = epoch + 18261.5 + tc / 1440.0;
+    let xnodce = (4.5236020 - 9.2422029e-4 * day) % TAU;
+    let stem = xnodce.sin();
+    let ctem = xnodce.cos();
+    let zcosil = 0.91375164 - 0.03568096 * ctem;
+    let zsinil = (1.0 - zcosil * zcosil).sqrt();
+    let zsinhl = 0.089683511 * stem / zsinil;
+    let zcoshl = (1.0 - zsinhl * zsinhl).sqrt();
+    let gam = 5.8351514 + 0.0019443680 * day;
+    let zx = 0.39785416 * stem / zsinil;
+    let zy = zcoshl * ctem + 0.91744867 * zsinhl * stem;
+    let zx = gam + zx.atan2(zy) - xnodce;
+    let zcosgl = zx.cos();
+    let zsingl = zx.sin();
+
+    // do solar terms
+    let mut zcosg = ZCOSGS;
+    let mut zsing = ZSINGS;
+    let mut zcosi = ZCOSIS;
+    let mut zsini = ZSINIS;
+    let mut zcosh = cnodm;
+    let mut zsinh = snodm;
+    let mut cc = C1SS;
+    let xnoi = 1.0 / nm;
+
+    // [s1..s7, z1, z2, z3, z11, z12, z13, z21, z22, z23, z31, z32, z33]
+    let mut solar = [0.0; 19];
+    let mut lunar = [0.0; 19];
+    for lsflg in 1..=2 {
+        let a1 = zcosg * zcosh + zsing * zcosi * zsinh;
+        let a3 = -zsing * zcosh + zcosg * zcosi * zsinh;
+        let a7 = -zcosg * zsinh + zsing * zcosi * zcosh;
+        let a8 = zsing * zsini;
+        let a9 = zsing * zsinh + zcosg * zcosi * zcosh;
+        let a10 = zcosg * zsini;
+        let a2 = cosim * a7 + sinim * a8;
+        let a4 = cosim * a9 + sinim * a10;
+        let a5 = -sinim * a7 + cosim * a8;
+        let a6 = -sinim * a9 + cosim * a10;
+
+        let x1 = a1 * cosomm + a2 * sinomm;
+        let x2 = a3 * cosomm + a4 * sinomm;
+        let x3 = -a1 * sinomm + a2 * cosomm;
+        let x4 = -a3 * sinomm + a4 * cosomm;
+        let x5 = a5 * sinomm;
+        let x6 = a6 * sinomm;
+        let x7 = a5 * cosomm;
+        let x8 = a6 * cosomm;
+
+        let z31 = 12.0 * x1 * x1 - 3.0 * x3 * x3;
+        let z32 = 24.0 * x1 * x2 - 6.0 * x3 * x4;
+        let z33 = 12.0 * x2 * x2 - 3.0 * x4 * x4;
+        let mut z1 = 3.0 * (a1 * a1 + a2 * a2) + z31 * emsq;
+        let mut z2 = 6.0 * (a1 * a3 + a2 * a4) + z32 * emsq;
+        let mut z3 = 3.0 * (a3 * a3 + a4 * a4) + z33 * emsq;
+        let z11 = -6.0 * a1 * a5 + emsq * (-24.0 * x1 * x7 - 6.0 * x3 * x5);
+        let z12 = -6.0 * (a1 * a6 + a3 * a5)
+            + emsq * (-24.0 * (x2 * x7 + x1 * x8) - 6.0 * (x3 * x6 + x4 * x5));
+        let z13 = -6.0 * a3 * a6 + emsq * (-24.0 * x2 * x8 - 6.0 * x4 * x6);
+        let z21 = 6.0 * a2 * a5 + emsq * (24.0 * x1 * x5 - 6.0 * x3 * x7);
+        let z22 = 6.0 * (a4 * a5 + a2 * a6)
+            + emsq * (24.0 * (x2 * x5 + x1 * x6) - 6.0 * (x4 * x7 + x3 * x8));
+        let z23 = 6.0 * a4 * a6 + emsq * (24.0 * x2 * x6 - 6.0 * x4 * x8);
+        z1 = z1 + z1 + betasq * z31;
+        z2 = z2 + z2 + betasq * z32;
+        z3 = z3 + z3 + betasq * z33;
+        let s3 = cc * xnoi;
+        let s2 = -0.5 * s3 / rtemsq;
+        let s4 = s3 * rtemsq;
+        let s1 = -15.0 * em * s4;
+        let s5 = x1 * x3 + x2 * x4;
+        let s6 = x2 * x3 + x1 * x4;
+        let s7 = x2 * x4 - x1 * x3;
+
+        let values = [
+            s1, s2, s3, s4, s5, s6, s7, z1, z2, z3, z11, z12, z13, z21, z22, z23, z31, z32, z33,
+        ];
+        if lsflg == 1 {
+            solar = values;
+            // do lunar terms
+            zcosg = zcosgl;
+            zsing = zsingl;
+            zcosi = zcosil;
+            zsini = zsinil;
+            zcosh = zcoshl * cnodm + zsinhl * snodm;
+            zsinh = snodm * zcoshl - cnodm * zsinhl;
+            cc = C1L;
+        } else {
+            lunar = values;
+        }
+    }
+
+    let [ss1, ss2, ss3, ss4, ss5, ss6, ss7, sz1, sz2, sz3, sz11, sz12, sz13, sz21, sz22, sz23, sz31, sz32, sz33] =
+        solar;
+    let [s1, s2, s3, s4, s5, s6, s7, z1, z2, z3, z11, z12, z13, z21, z22, z23, z31, z32, z33] =
+        lunar;
+
+    let terms = DeepSpace {
+        zmol: (4.7199672 + 0.22997150 * day - gam) % TAU,
+        zmos: (6.2565837 + 0.017201977 * day) % TAU,
+        // do solar terms
+        se2: 2.0 * ss1 * ss6,
+        se3: 2.0 * ss1 * ss7,
+        si2: 2.0 * ss2 * sz12,
+        si3: 2.0 * ss2 * (sz13 - sz11),
+        sl2: -2.0 * ss3 * sz2,
+        sl3: -2.0 * ss3 * (sz3 - sz1),
+        sl4: -2.0 * ss3 * (-21.0 - 9.0 * emsq) * ZES,
+        sgh2: 2.0 * ss4 * sz32,
+        sgh3: 2.0 * ss4 * (sz33 - sz31),
+        sgh4: -18.0 * ss4 * ZES,
+        sh2: -2.0 * ss2 * sz22,
+        sh3: -2.0 * ss2 * (sz23 - sz21),
+        // do lunar terms
+        ee2: 2.0 * s1 * s6,
+        e3: 2.0 * s1 * s7,
+        xi2: 2.0 * s2 * z12,
+        xi3: 2.0 * s2 * (z13 - z11),
+        xl2: -2.0 * s3 * z2,
+        xl3: -2.0 * s3 * (z3 - z1),
+        xl4: -2.0 * s3 * (-21.0 - 9.0 * emsq) * ZEL,
+        xgh2: 2.0 * s4 * z32,
+        xgh3: 2.0 * s4 * (z33 - z31),
+        xgh4: -18.0 * s4 * ZEL,
+        xh2: -2.0 * s2 * z22,
+        xh3: -2.0 * s2 * (z23 - z21),
+        ..Default::default()
+    };
+
+    Dscom {
+        sinim,
+        cosim,
+        emsq,
+        s1,
+        s2,
+        s3,
+        s4,
+        s5,
+        ss1,
+        ss2,
+        ss3,
+        ss4,
+        ss5,
+        sz1,
+        sz3,
+        sz11,
+        sz13,
+        sz21,
+        sz23,
+        sz31,
+        sz33,
+        z1,
+        z3,
+        z11,
+        z13,
+        z21,
+        z23,
+        z31,
+        z33,
+        em,
+        nm,
+        inclm: inclp,
+        terms,
+    }
+}
+
+/// Apply the lunar-solar periodics (Vallado's `dpper`, with `init == 'n'`).
+fn dpper(
+    ds: &DeepSpace,
+    t: f64,
+    ep: &mut f64,
+    inclp: &mut f64,
+    nodep: &mut f64,
+    argpp: &mut f64,
+    mp: &mut f64,
+) {
+    const ZNS: f64 = 1.19459e-5;
+    const ZES: f64 = 0.01675;
+    const ZNL: f64 = 1.5835218e-4;
+    const ZEL: f64 = 0.05490;
+
+    // calculate time varying periodics
+    let zm = ds.zmos + ZNS * t;
+    let zf = zm + 2.0 * ZES * zm.sin();
+    let sinzf = zf.sin();
+    let f2 = 0.5 * sinzf * sinzf - 0.25;
+    let f3 = -0.5 * sinzf * zf.cos();
+    let ses = ds.se2 * f2 + ds.se3 * f3;
+    let sis = ds.si2 * f2 + ds.si3 * f3;
+    let sls = ds.sl2 * f2 + ds.sl3 * f3 + ds.sl4 * sinzf;
+    let sghs = ds.sgh2 * f2 + ds.sgh3 * f3 + ds.sgh4 * sinzf;
+    let shs = ds.sh2 * f2 + ds.sh3 * f3;
+
+    let zm = ds.zmol + ZNL * t;
+    let zf = zm + 2.0 * ZEL * zm.sin();
+    let sinzf = zf.sin();
+    let f2 = 0.5 * sinzf * sinzf - 0.25;
+    let f3 = -0.5 * sinzf * zf.cos();
+    let sel = ds.ee2 * f2 + ds.e3 * f3;
+    let sil = ds.xi2 * f2 + ds.xi3 * f3;
+    let sll = ds.xl2 * f2 + ds.xl3 * f3 + ds.xl4 * sinzf;
+    let sghl = ds.xgh2 * f2 + ds.xgh3 * f3 + ds.xgh4 * sinzf;
+    let shll = ds.xh2 * f2 + ds.xh3 * f3;
+
+    let pe = ses + sel;
+    let pinc = sis + sil;
+    let pl = sls + sll;
+    let mut pgh = sghs + sghl;
+    let mut ph = shs + shll;
+
+    *inclp += pinc;
+    *ep += pe;
+    let sinip = inclp.sin();
+    let cosip = inclp.cos();
+
+    if *inclp >= 0.2 {
+        // apply periodics directly
+        ph /= sinip;
+        pgh -= cosip * ph;
+        *argpp += pgh;
+        *nodep += ph;
+        *mp += pl;
+    } else {
+        // apply periodics with lyddane modification
+        let sinop = nodep.sin();
+        let cosop = nodep.cos();
+        let mut alfdp = sinip * sinop;
+        let mut betdp = sinip * cosop;
+        let dalf = ph * cosop + pinc * cosip * sinop;
+        let dbet = -ph * sinop + pinc * cosip * cosop;
+        alfdp += dalf;
+        betdp += dbet;
+        *nodep %= TAU;
+        let mut xls = *mp + *argpp + cosip * *nodep;
+        let dls = pl + pgh - pinc * *nodep * sinip;
+        xls += dls;
+        let xnoh = *nodep;
+        *nodep = alfdp.atan2(betdp);
+        if (xnoh - *nodep).abs() > PI {
+            if *nodep < xnoh {
+                *nodep += TAU;
+            } else {
+                *nodep -= TAU;
+            }
+        }
+        *mp += pl;
+        *argpp = xls - *mp - cosip * *nodep;
+    }
+}
+
+struct DsinitInput {
+    argpo: f64,
+    gsto: f64,
+    mo: f64,
+    mdot: f64,
+    no: f64,
+    nodeo: f64,
+    nodedot: f64,
+    xpidot: f64,
+    ecco: f64,
+    eccsq: f64,
+}
+
+/// Deep space secular rates and resonance terms (Vallado's `dsinit`, at `t == 0`).
+fn dsinit(com: &Dscom, input: DsinitInput) -> DeepSpace {
+    const Q22: f64 = 1.7891679e-6;
+    const Q31: f64 = 2.1460748e-6;
+    const Q33: f64 = 2.2123015e-7;
+    const ROOT22: f64 = 1.7891679e-6;
+    const ROOT44: f64 = 7.3636953e-9;
+    const ROOT54: f64 = 2.1765803e-9;
+    const RPTIM: f64 = 4.375_269_088_011_3e-3;
+    const ROOT32: f64 = 3.7393792e-7;
+    const ROOT52: f64 = 1.1428639e-7;
+    const ZNL: f64 = 1.5835218e-4;
+    const ZNS: f64 = 1.19459e-5;
+
+    let DsinitInput {
+        argpo,
+        gsto,
+        mo,
+        mdot,
+        no,
+        nodeo,
+        nodedot,
+        xpidot,
+        ecco,
+        eccsq,
+    } = input;
+    let Dscom {
+        sinim,
+        cosim,
+        emsq,
+        s1,
+        s2,
+        s3,
+        s4,
+        s5,
+        ss1,
+        ss2,
+        ss3,
+        ss4,
+        ss5,
+        sz1,
+        sz3,
+        sz11,
+        sz13,
+        sz21,
+        sz23,
+        sz31,
+        sz33,
+        z1,
+        z3,
+        z11,
+        z13,
+        z21,
+        z23,
+        z31,
+        z33,
+        em,
+        nm,
+        inclm,
+        ..
+    } = *com;
+    let mut ds = com.terms.clone();
+
+    let xke = xke();
+
+    // deep space initialization
+    ds.irez = 0;
+    if nm < 0.0052359877 && nm > 0.0034906585 {
+        ds.irez = 1;
+    }
+    if (8.26e-3..=9.24e-3).contains(&nm) && em >= 0.5 {
+        ds.irez = 2;
+    }
+
+    // do solar terms
+    let ses = ss1 * ZNS * ss5;
+    let sis = ss2 * ZNS * (sz11 + sz13);
+    let sls = -ZNS * ss3 * (sz1 + sz3 - 14.0 - 6.0 * emsq);
+    let sghs = ss4 * ZNS * (sz31 + sz33 - 6.0);
+    let mut shs = -ZNS * ss2 * (sz21 + sz23);
+    if !(5.2359877e-2..=PI - 5.2359877e-2).contains(&inclm) {
+        shs = 0.0;
+    }
+    if sinim != 0.0 {
+        shs /= sinim;
+    }
+    let sgs = sghs - cosim * shs;
+
+    // do lunar terms
+    ds.dedt = ses + s1 * ZNL * s5;
+    ds.didt = sis + s2 * ZNL * (z11 + z13);
+    ds.dmdt = sls - ZNL * s3 * (z1 + z3 - 14.0 - 6.0 * emsq);
+    let sghl = s4 * ZNL * (z31 + z33 - 6.0);
+    let mut shll = -ZNL * s2 * (z21 + z23);
+    if !(5.2359877e-2..=PI - 5.2359877e-2).contains(&inclm) {
+        shll = 0.0;
+    }
+    ds.domdt = sgs + sghl;
+    ds.dnodt = shs;
+    if sinim != 0.0 {
+        ds.domdt -= cosim / sinim * shll;
+        ds.dnodt += shll / sinim;
+    }
+
+    // calculate deep space resonance effects
+    let theta = gsto % TAU;
+
+    if ds.irez != 0 {
+        let aonv = (nm / xke).powf(X2O3);
+
+        // geopotential resonance for 12 hour orbits
+        if ds.irez == 2 {
+            let cosisq = cosim * cosim;
+            let em = ecco;
+            let emsq = eccsq;
+            let eoc = em * emsq;
+            let g201 = -0.306 - (em - 0.64) * 0.440;
+
+            let (g211, g310, g322, g410, g422, g520);
+            if em <= 0.65 {
+                g211 = 3.616 - 13.2470 * em + 16.2900 * emsq;
+                g310 = -19.302 + 117.3900 * em - 228.4190 * emsq + 156.5910 * eoc;
+                g322 = -18.9068 + 109.7927 * em - 214.6334 * emsq + 146.5816 * eoc;
+                g410 = -41.122 + 242.6940 * em - 471.0940 * emsq + 313.9530 * eoc;
+                g422 = -146.407 + 841.8800 * em - 1629.014 * emsq + 1083.4350 * eoc;
+                g520 = -532.114 + 3017.977 * em - 5740.032 * emsq + 3708.2760 * eoc;
+            } else {
+                g211 = -72.099 + 331.819 * em - 508.738 * emsq + 266.724 * eoc;
+                g310 = -346.844 + 1582.851 * em - 2415.925 * emsq + 1246.113 * eoc;
+                g322 = -342.585 + 1554.908 * em - 2366.899 * emsq + 1215.972 * eoc;
+                g410 = -1052.797 + 4758.686 * em - 7193.992 * emsq + 3651.957 * eoc;
+                g422 = -3581.690 + 16178.110 * em - 24462.770 * emsq + 12422.520 * eoc;
+                g520 = if em > 0.715 {
+                    -5149.66 + 29936.92 * em - 54087.36 * emsq + 31324.56 * eoc
+                } else {
+                    1464.74 - 4664.75 * em + 3763.64 * emsq
+                };
+            }
+            let (g533, g521, g532);
+            if em < 0.7 {
+                g533 = -919.22770 + 4988.6100 * em - 9064.7700 * emsq + 5542.21 * eoc;
+                g521 = -822.71072 + 4568.6173 * em - 8491.4146 * emsq + 5337.524 * eoc;
+                g532 = -853.66600 + 4690.2500 * em - 8624.7700 * emsq + 5341.4 * eoc;
+            } else {
+                g533 = -37995.780 + 161616.52 * em - 229838.20 * emsq + 109377.94 * eoc;
+                g521 = -51752.104 + 218913.95 * em - 309468.16 * emsq + 146349.42 * eoc;
+                g532 = -40023.880 + 170470.89 * em - 242699.48 * emsq + 115605.82 * eoc;
+            }
+
+            let sini2 = sinim * sinim;
+            let f220 = 0.75 * (1.0 + 2.0 * cosim + cosisq);
+            let f221 = 1.5 * sini2;
+            let f321 = 1.875 * sinim * (1.0 - 2.0 * cosim - 3.0 * cosisq);
+            let f322 = -1.875 * sinim * (1.0 + 2.0 * cosim - 3.0 * cosisq);
+            let f441 = 35.0 * sini2 * f220;
+            let f442 = 39.3750 * sini2 * sini2;
+            let f522 = 9.84375
+                * sinim
+                * (sini2 * (1.0 - 2.0 * cosim - 5.0 * cosisq)
+                    + 0.33333333 * (-2.0 + 4.0 * cosim + 6.0 * cosisq));
+            let f523 = sinim
+                * (4.92187512 * sini2 * (-2.0 - 4.0 * cosim + 10.0 * cosisq)
+                    + 6.56250012 * (1.0 + 2.0 * cosim - 3.0 * cosisq));
+            let f542 = 29.53125
+                * sinim
+                * (2.0 - 8.0 * cosim + cosisq * (-12.0 + 8.0 * cosim + 10.0 * cosisq));
+            let f543 = 29.53125
+                * sinim
+                * (-2.0 - 8.0 * cosim + cosisq * (12.0 + 8.0 * cosim - 10.0 * cosisq));
+            let xno2 = nm * nm;
+            let ainv2 = aonv * aonv;
+            let mut temp1 = 3.0 * xno2 * ainv2;
+            let mut temp = temp1 * ROOT22;
+            ds.d2201 = temp * f220 * g201;
+            ds.d2211 = temp * f221 * g211;
+            temp1 *= aonv;
+            temp = temp1 * ROOT32;
+            ds.d3210 = temp * f321 * g310;
+            ds.d3222 = temp * f322 * g322;
+            temp1 *= aonv;
+            temp = 2.0 * temp1 * ROOT44;
+            ds.d4410 = temp * f441 * g410;
+            ds.d4422 = temp * f442 * g422;
+            temp1 *= aonv;
+            temp = temp1 * ROOT52;
+            ds.d5220 = temp * f522 * g520;
+            ds.d5232 = temp * f523 * g532;
+            temp = 2.0 * temp1 * ROOT54;
+            ds.d5421 = temp * f542 * g521;
+            ds.d5433 = temp * f543 * g533;
+            ds.xlamo = (mo + nodeo + nodeo - theta - theta) % TAU;
+            ds.xfact = mdot + ds.dmdt + 2.0 * (nodedot + ds.dnodt - RPTIM) - no;
+        }
+
+        // synchronous resonance terms
+        if ds.irez == 1 {
+            let g200 = 1.0 + emsq * (-2.5 + 0.8125 * emsq);
+            let g310 = 1.0 + 2.0 * emsq;
+            let g300 = 1.0 + emsq * (-6.0 + 6.60937 * emsq);
+            let f220 = 0.75 * (1.0 + cosim) * (1.0 + cosim);
+            let f311 = 0.9375 * sinim * sinim * (1.0 + 3.0 * cosim) - 0.75 * (1.0 + cosim);
+            let f330 = 1.875 * (1.0 + cosim).powi(3);
+            let del1 = 3.0 * nm * nm * aonv * aonv;
+            ds.del2 = 2.0 * del1 * f220 * g200 * Q22;
+            ds.del3 = 3.0 * del1 * f330 * g300 * Q33 * aonv;
+            ds.del1 = del1 * f311 * g310 * Q31 * aonv;
+            ds.xlamo = (mo + nodeo + argpo - theta) % TAU;
+            ds.xfact = mdot + xpidot - RPTIM + ds.dmdt + ds.domdt + ds.dnodt - no;
+        }
+    }
+
+    ds
+}
+
+struct DspaceState {
+    argpo: f64,
+    argpdot: f64,
+    t: f64,
+    gsto: f64,
+    no: f64,
+}
+
+/// Deep space secular effects and resonance integration (Vallado's `dspace`).
+///
+/// The resonance integrator always restarts from epoch, so the propagator stays stateless.
+#[allow(clippy::too_many_arguments)]
+fn dspace(
+    ds: &DeepSpace,
+    state: DspaceState,
+    em: &mut f64,
+    argpm: &mut f64,
+    inclm: &mut f64,
+    mm: &mut f64,
+    nodem: &mut f64,
+    nm: &mut f64,
+) {
+    const FASX2: f64 = 0.13130908;
+    const FASX4: f64 = 2.8843198;
+    const FASX6: f64 = 0.37448087;
+    const G22: f64 = 5.7686396;
+    const G32: f64 = 0.95240898;
+    const G44: f64 = 1.8014998;
+    const G52: f64 = 1.0508330;
+    const G54: f64 = 4.4108898;
+    const RPTIM: f64 = 4.375_269_088_011_3e-3;
+    const STEPP: f64 = 720.0;
+    const STEPN: f64 = -720.0;
+    const STEP2: f64 = 259200.0;
+
+    let DspaceState {
+        argpo,
+        argpdot,
+        t,
+        gsto,
+        no,
+    } = state;
+
+    // calculate deep space resonance effects
+    let theta = (gsto + t * RPTIM) % TAU;
+    *em += ds.dedt * t;
+    *inclm += ds.didt * t;
+    *argpm += ds.domdt * t;
+    *nodem += ds.dnodt * t;
+    *mm += ds.dmdt * t;
+
+    if ds.irez == 0 {
+        return;
+    }
+
+    // update resonances: numerical (euler-maclaurin) integration from epoch
+    let mut atime = 0.0;
+    let mut xni = no;
+    let mut xli = ds.xlamo;
+    let delt = if t > 0.0 { STEPP } else { STEPN };
+    let (mut xndt, mut xldot, mut xnddt);
+    let ft;
+    loop {
+        if ds.irez != 2 {
+            // near - synchronous resonance terms
+            xndt = ds.del1 * (xli - FASX2).sin()
+                + ds.del2 * (2.0 * (xli - FASX4)).sin()
+                + ds.del3 * (3.0 * (xli - FASX6)).sin();
+            xldot = xni + ds.xfact;
+            xnddt = ds.del1 * (xli - FASX2).cos()
+                + 2.0 * ds.del2 * (2.0 * (xli - FASX4)).cos()
+                + 3.0 * ds.del3 * (3.0 * (xli - FASX6)).cos();
+            xnddt *= xldot;
+        } else {
+            // near - half-day resonance terms
+            let xomi = argpo + argpdot * atime;
+            let x2omi = xomi + xomi;
+            let x2li = xli + xli;
+            xndt = ds.d2201 * (x2omi + xli - G22).sin()
+                + ds.d2211 * (xli - G22).sin()
+                + ds.d3210 * (xomi + xli - G32).sin()
+                + ds.d3222 * (-xomi + xli - G32).sin()
+                + ds.d4410 * (x2omi + x2li - G44).sin()
+                + ds.d4422 * (x2li - G44).sin()
+                + ds.d5220 * (xomi + xli - G52).sin()
+                + ds.d5232 * (-xomi + xli - G52).sin()
+                + ds.d5421 * (xomi + x2li - G54).sin()
+                + ds.d5433 * (-xomi + x2li - G54).sin();
+            xldot = xni + ds.xfact;
+            xnddt = ds.d2201 * (x2omi + xli - G22).cos()
+                + ds.d2211 * (xli - G22).cos()
+                + ds.d3210 * (xomi + xli - G32).cos()
+                + ds.d3222 * (-xomi + xli - G32).cos()
+                + ds.d5220 * (xomi + xli - G52).cos()
+                + ds.d5232 * (-xomi + xli - G52).cos()
+                + 2.0
+                    * (ds.d4410 * (x2omi + x2li - G44).cos()
+                        + ds.d4422 * (x2li - G44).cos()
+                        + ds.d5421 * (xomi + x2li - G54).cos()
+                        + ds.d5433 * (-xomi + x2li - G54).cos());
+            xnddt *= xldot;
+        }
+
+        if (t - atime).abs() < STEPP {
+            ft = t - atime;
+            break;
+        }
+        xli += xldot * delt + xndt * STEP2;
+        xni += xndt * delt + xnddt * STEP2;
+        atime += delt;
+    }
+
+    *nm = xni + xndt * ft + xnddt * ft * ft * 0.5;
+    let xl = xli + xldot * ft + xndt * ft * ft * 0.5;
+    if ds.irez != 1 {
+        *mm = xl - 2.0 * *nodem + 2.0 * theta;
+    } else {
+        *mm = xl - *nodem - *argpm + theta;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEG: f64 = PI / 180.0;
+
+    fn norm(v: [f64; 3]) -> f64 {
+        (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+    }
+
+    /// Vallado's verification case 00005 (near earth, eccentric).
+    #[test]
+    fn test_vanguard_verification() {
+        let sgp4 = Sgp4::new(&Sgp4Elements {
+            epoch: 18441.78495062, // 2000-179.78495062
+            bstar: 0.28098e-4,
+            eccentricity: 0.1859667,
+            argument_of_perigee: 331.7664 * DEG,
+            inclination: 34.2682 * DEG,
+            mean_anomaly: 19.3264 * DEG,
+            mean_motion: 10.82419157 * TAU / 1440.0,
+            raan: 348.7242 * DEG,
+        })
+        .unwrap();
+        assert!(!sgp4.is_deep_space());
+
+        let expected = [
+            (0.0, [7022.46529266, -1400.08296755, 0.03995155]),
+            (360.0, [-7154.03120202, -3783.17682504, -3536.19412294]),
+        ];
+        for (tsince, position) in expected {
+            let prediction = sgp4.propagate(tsince).unwrap();
+            for (got, want) in prediction.position.iter().zip(position) {
+                assert!(
+                    (got - want).abs() < 1e-3,
+                    "{} != {} at {}",
+                    got,
+                    want,
+                    tsince
+                );
+            }
+        }
+    }
+
+    /// A geostationary satellite must stay at the GEO radius with SDP4.
+    #[test]
+    fn test_deep_space_geostationary() {
+        let sgp4 = Sgp4::new(&Sgp4Elements {
+            epoch: 27000.5,
+            bstar: 0.0,
+            eccentricity: 0.0002,
+            argument_of_perigee: 100.0 * DEG,
+            inclination: 0.05 * DEG,
+            mean_anomaly: 200.0 * DEG,
+            mean_motion: 1.00271 * TAU / 1440.0,
+            raan: 90.0 * DEG,
+        })
+        .unwrap();
+        assert!(sgp4.is_deep_space());
+
+        for tsince in [0.0, 720.0, 1440.0 * 3.0] {
+            let radius = norm(sgp4.propagate(tsince).unwrap().position);
+            assert!((radius - 42164.0).abs() < 50.0, "radius: {}", radius);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::{ecs::entity::EntityHashMap, platform::collections::HashMap};
+
+use super::*;
+
+const CELLS_PER_RANGE: f32 = 2.0; // 每个连接距离划分的网格数
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>().add_systems(
+            FixedUpdate,
+            rebuild_spatial_index
+                .after(update_satellite_position)
+                .after(propagate_sgp4),
+        );
+    }
+}
+
+/// A uniform grid over satellite positions, for range queries.
+/// Rebuilt every `FixedUpdate` from the positions of the same step, with cells a fraction of
+/// `connection_distance`.
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<usize>>,
+    entries: Vec<(Entity, Vec3)>,
+    indices: EntityHashMap<usize>,
+}
+
+impl SpatialIndex {
+    /// Rebuild the grid, reusing the allocations.
+    pub fn rebuild(&mut self, cell_size: f32, positions: impl IntoIterator<Item = (Entity, Vec3)>) {
+        self.cell_size = cell_size.max(f32::EPSILON);
+        self.cells.values_mut().for_each(Vec::clear);
+        self.entries.clear();
+        self.indices.clear();
+
+        for (entity, position) in positions {
+            let i = self.entries.len();
+            self.entries.push((entity, position));
+            self.indices.insert(entity, i);
+            let cell = self.cell_of(position);
+            self.cells.entry(cell).or_default().push(i);
+        }
+        // drop cells left empty by moved satellites
+        self.cells.retain(|_, entries| !entries.is_empty());
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    /// Position of an indexed entity.
+    pub fn position(&self, entity: Entity) -> Option<Vec3> {
+        self.indices.get(&entity).map(|&i| self.entries[i].1)
+    }
+
+    /// All entities within `radius` of `center`, with their squared distances. Unordered.
+    pub fn within(&self, center: Vec3, radius: f32) -> impl Iterator<Item = (Entity, f32)> + '_ {
+        let min = self.cell_of(center - Vec3::splat(radius));
+        let max = self.cell_of(center + Vec3::splat(radius));
+        let radius_sq = radius * radius;
+        (min.x..=max.x)
+            .flat_map(move |x| {
+                (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter_map(move |&i| {
+                let (entity, position) = self.entries[i];
+                let distance_sq = position.distance_squared(center);
+                (distance_sq <= radius_sq).then_some((entity, distance_sq))
+            })
+    }
+}
+
+pub(super) fn rebuild_spatial_index(
+    config: Res<Config>,
+    mut index: ResMut<SpatialIndex>,
+    satellites: Query<(Entity, &Transform), With<Satellite>>,
+) {
+    // satellites are root entities, `Transform` is up to date while `GlobalTransform` is only
+    // propagated in `PostUpdate`
+    index.rebuild(
+        config.simulation.connection_distance / CELLS_PER_RANGE,
+        satellites
+            .iter()
+            .map(|(sat, transform)| (sat, transform.translation)),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn random_shell(n: usize) -> Vec<(Entity, Vec3)> {
+        let mut rng = rand::rng();
+        (0..n)
+            .map(|i| {
+                let direction = Vec3::new(
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                )
+                .normalize_or(Vec3::X);
+                let radius = rng.random_range(6800.0..7400.0);
+                (Entity::from_raw(i as u32), direction * radius)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_within() {
+        let positions = random_shell(2000);
+        let mut index = SpatialIndex::default();
+        index.rebuild(500.0, positions.clone());
+        let center = positions[0].1;
+
+        let mut found: Vec<Entity> = index.within(center, 800.0).map(|(e, _)| e).collect();
+        let mut expected: Vec<Entity> = positions
+            .iter()
+            .filter(|(_, p)| p.distance_squared(center) <= 800.0 * 800.0)
+            .map(|(e, _)| *e)
+            .collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(index.position(positions[7].0), Some(positions[7].1));
+    }
+
+    /// Run with `cargo test --release bench_spatial_index -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_spatial_index() {
+        const N: usize = 30_000;
+        const DISTANCE: f32 = 2000.0;
+        let positions = random_shell(N);
+        let queries = N / 5; // about `TryConnect` satellites per tick
+
+        let start = Instant::now();
+        let mut naive = 0;
+        for (_, center) in positions.iter().take(queries) {
+            naive += positions
+                .iter()
+                .filter(|(_, p)| p.distance_squared(*center) <= DISTANCE * DISTANCE)
+                .count();
+        }
+        let naive_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut index = SpatialIndex::default();
+        index.rebuild(DISTANCE / CELLS_PER_RANGE, positions.iter().copied());
+        let build_time = start.elapsed();
+        let mut grid = 0;
+        for (_, center) in positions.iter().take(queries) {
+            grid += index.within(*center, DISTANCE).count();
+        }
+        let grid_time = start.elapsed();
+
+        assert_eq!(naive, grid);
+        println!(
+            "{} satellites, {} queries: naive {:?}, grid {:?} (build {:?}), {:.1}x",
+            N,
+            queries,
+            naive_time,
+            grid_time,
+            build_time,
+            naive_time.as_secs_f64() / grid_time.as_secs_f64()
+        );
+    }
+}
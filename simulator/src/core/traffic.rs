@@ -0,0 +1,576 @@
+use bevy::platform::collections::HashMap;
+use rand::Rng;
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    fs,
+};
+
+use super::{link_budget::LinkBudget, *};
+
+/// Packets are dropped after this many hops, in case of loops while routing tables change.
+const MAX_HOPS: u32 = 64;
+
+pub struct TrafficPlugin;
+
+impl Plugin for TrafficPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(FixedPostUpdate, simulate_traffic.after(update_routing));
+    }
+}
+
+/// Statistics of a flow since the start of the simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowStats {
+    pub name: String,
+    pub generated: u64,
+    pub delivered: u64,
+    pub dropped_queue: u64,      // 队列溢出丢包数
+    pub dropped_no_route: u64,   // 无路由丢包数
+    pub dropped_link_down: u64,  // 链路断开丢包数
+    pub throughput: f64,         // 平均吞吐量(bit/s)
+    pub mean_delay: Option<f64>, // 平均端到端时延(s)
+    pub max_delay: Option<f64>,  // 最大端到端时延(s)
+    pub loss_rate: f64,
+}
+
+/// A traffic generator and its counters.
+struct Flow {
+    config: FlowConfig,
+    trace: Vec<(f64, usize)>,            // (相对开始的时间(s), 包长(bytes))
+    next_trace: usize,                   // 下一个trace包的序号
+    next_time: f64,                      // 下一个包的生成时间(s)
+    endpoints: Option<(Entity, Entity)>, // 按名字找到的源和目的
+    generated: u64,
+    delivered: u64,
+    delivered_bytes: u64,
+    dropped_queue: u64,
+    dropped_no_route: u64,
+    dropped_link_down: u64,
+    total_delay: f64,
+    max_delay: f64,
+}
+
+impl Flow {
+    fn new(config: &FlowConfig) -> Result<Self> {
+        // NaN fails every comparison, so test for the valid range
+        let positive = |value: f64| value > 0.0 && value.is_finite();
+        let trace = match &config.generator {
+            TrafficGenerator::Poisson { rate } if !positive(*rate) => {
+                return Err(format!("Flow {}: rate must be positive", config.name).into());
+            }
+            TrafficGenerator::ConstantBitrate { bitrate } if !positive(*bitrate) => {
+                return Err(format!("Flow {}: bitrate must be positive", config.name).into());
+            }
+            TrafficGenerator::Trace { trace_file } => read_trace(trace_file)?,
+            _ if config.packet_size == 0 => {
+                return Err(format!("Flow {}: packet_size must be positive", config.name).into());
+            }
+            _ => vec![],
+        };
+        Ok(Self {
+            config: config.clone(),
+            trace,
+            next_trace: 0,
+            next_time: config.start,
+            endpoints: None,
+            generated: 0,
+            delivered: 0,
+            delivered_bytes: 0,
+            dropped_queue: 0,
+            dropped_no_route: 0,
+            dropped_link_down: 0,
+            total_delay: 0.0,
+            max_delay: 0.0,
+        })
+    }
+
+    /// Time between two packets.
+    fn interval(&self, rng: &mut impl Rng) -> f64 {
+        match self.config.generator {
+            TrafficGenerator::Poisson { rate } => -(1.0 - rng.random::<f64>()).ln() / rate,
+            TrafficGenerator::ConstantBitrate { bitrate } => {
+                self.config.packet_size as f64 * 8.0 / bitrate
+            }
+            TrafficGenerator::Trace { .. } => f64::INFINITY,
+        }
+    }
+
+    /// Restart the generator at `now`, skipping packets before it.
+    fn reschedule(&mut self, now: f64, rng: &mut impl Rng) {
+        let start = self.config.start.max(now);
+        self.next_time = match self.config.generator {
+            TrafficGenerator::Poisson { .. } => start + self.interval(rng),
+            _ => start,
+        };
+        self.next_trace = self
+            .trace
+            .partition_point(|(offset, _)| self.config.start + offset < now);
+    }
+
+    /// The next packet (time, size) generated before `until`.
+    fn next_packet(&mut self, until: f64, rng: &mut impl Rng) -> Option<(f64, usize)> {
+        let until = self.config.stop.map_or(until, |stop| stop.min(until));
+        if let TrafficGenerator::Trace { .. } = self.config.generator {
+            let &(offset, size) = self.trace.get(self.next_trace)?;
+            let time = self.config.start + offset;
+            if time >= until {
+                return None;
+            }
+            self.next_trace += 1;
+            return Some((time, size));
+        }
+        if self.next_time >= until {
+            return None;
+        }
+        let time = self.next_time;
+        self.next_time += self.interval(rng);
+        Some((time, self.config.packet_size))
+    }
+
+    fn stats(&self, now: f64) -> FlowStats {
+        let end = self.config.stop.map_or(now, |stop| stop.min(now));
+        let duration = end - self.config.start;
+        let dropped = self.dropped_queue + self.dropped_no_route + self.dropped_link_down;
+        let finished = self.delivered + dropped;
+        FlowStats {
+            name: self.config.name.clone(),
+            generated: self.generated,
+            delivered: self.delivered,
+            dropped_queue: self.dropped_queue,
+            dropped_no_route: self.dropped_no_route,
+            dropped_link_down: self.dropped_link_down,
+            throughput: if duration > 0.0 {
+                self.delivered_bytes as f64 * 8.0 / duration
+            } else {
+                0.0
+            },
+            mean_delay: (self.delivered > 0).then(|| self.total_delay / self.delivered as f64),
+            max_delay: (self.delivered > 0).then_some(self.max_delay),
+            loss_rate: if finished > 0 {
+                dropped as f64 / finished as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Read a trace of `time,size` lines, lines starting with `#` are skipped.
+fn read_trace(path: &str) -> Result<Vec<(f64, usize)>> {
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("Trace file {}: {}", path, err))?;
+    let mut trace = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (time, size) = line
+                .split_once(',')
+                .ok_or_else(|| format!("Invalid line in trace file {}: {}", path, line))?;
+            Ok((time.trim().parse::<f64>()?, size.trim().parse::<usize>()?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    trace.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(trace)
+}
+
+struct Packet {
+    id: u64,
+    flow: usize,
+    size: usize,  // 包长(bytes)
+    created: f64, // 生成时间(s)
+    destination: Entity,
+    hops: u32,
+}
+
+/// A packet arriving at a node, over `link` unless it is generated there.
+struct Arrival {
+    time: f64,
+    node: Entity,
+    link: Option<(Entity, Entity)>,
+    packet: Packet,
+}
+
+impl PartialEq for Arrival {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Arrival {}
+
+impl PartialOrd for Arrival {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Arrival {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for a min-heap, ties broken by packet for determinism
+        other
+            .time
+            .total_cmp(&self.time)
+            .then_with(|| other.packet.id.cmp(&self.packet.id))
+    }
+}
+
+/// The FIFO queue of one link direction, served at the link capacity.
+#[derive(Default)]
+struct LinkQueue {
+    queue: VecDeque<(f64, usize)>, // (发送完成时间(s), 包长(bytes))
+    bytes: usize,                  // 队列中的字节数
+    busy_until: f64,               // 链路空闲的时间(s)
+}
+
+/// Packet-level traffic over the network.
+/// Packets are forwarded hop by hop along the first path of the current `Routing`, queued
+/// at each link and sent at its Shannon capacity. They are dropped when the queue is full,
+/// when there is no route, or when the link they are on is torn down before they arrive.
+#[derive(Resource)]
+pub struct Traffic {
+    queue_size: usize, // 每条链路的队列长度(bytes)
+    flows: Vec<Flow>,
+    links: HashMap<(Entity, Entity), LinkQueue>,
+    arrivals: BinaryHeap<Arrival>,
+    next_id: u64,
+    time: f64,  // 已模拟到的仿真时间(s)
+    jumps: u32, // 已处理的时钟跳转次数
+}
+
+impl Traffic {
+    fn new(config: &TrafficConfig) -> Result<Self> {
+        Ok(Self {
+            queue_size: config.queue_size,
+            flows: config.flows.iter().map(Flow::new).collect::<Result<_>>()?,
+            links: default(),
+            arrivals: default(),
+            next_id: 0,
+            time: 0.0,
+            jumps: 0,
+        })
+    }
+
+    /// Statistics of every flow.
+    pub fn stats(&self) -> Vec<FlowStats> {
+        self.flows
+            .iter()
+            .map(|flow| flow.stats(self.time))
+            .collect()
+    }
+
+    /// Discard packets in flight and restart the generators at `now`.
+    fn reset(&mut self, now: f64, rng: &mut impl Rng) {
+        self.links.clear();
+        self.arrivals.clear();
+        for flow in &mut self.flows {
+            flow.reschedule(now, rng);
+        }
+        self.time = now;
+    }
+
+    /// Generate the packets of every flow until `now`.
+    fn generate(&mut self, now: f64, rng: &mut impl Rng) {
+        for (i, flow) in self.flows.iter_mut().enumerate() {
+            while let Some((time, size)) = flow.next_packet(now, rng) {
+                // skipped until both ends exist
+                let Some((from, to)) = flow.endpoints else {
+                    continue;
+                };
+                flow.generated += 1;
+                self.arrivals.push(Arrival {
+                    time,
+                    node: from,
+                    link: None,
+                    packet: Packet {
+                        id: self.next_id,
+                        flow: i,
+                        size,
+                        created: time,
+                        destination: to,
+                        hops: 0,
+                    },
+                });
+                self.next_id += 1;
+            }
+        }
+    }
+
+    /// Forward packets until `now`. `next_hop(node, destination)` looks up the routes.
+    fn forward(
+        &mut self,
+        now: f64,
+        graph: &NetworkGraph,
+        link_budget: &LinkBudgetConfig,
+        mut next_hop: impl FnMut(Entity, Entity) -> Option<Entity>,
+    ) {
+        while self
+            .arrivals
+            .peek()
+            .is_some_and(|arrival| arrival.time <= now)
+        {
+            let Some(Arrival {
+                time,
+                node,
+                link,
+                packet,
+            }) = self.arrivals.pop()
+            else {
+                break;
+            };
+            let flow = &mut self.flows[packet.flow];
+
+            if link.is_some_and(|(from, to)| graph.delay(from, to).is_none()) {
+                flow.dropped_link_down += 1;
+                continue;
+            }
+            if node == packet.destination {
+                let delay = time - packet.created;
+                flow.delivered += 1;
+                flow.delivered_bytes += packet.size as u64;
+                flow.total_delay += delay;
+                flow.max_delay = flow.max_delay.max(delay);
+                continue;
+            }
+            let next = (packet.hops < MAX_HOPS)
+                .then(|| next_hop(node, packet.destination))
+                .flatten()
+                .and_then(|next| Some((next, graph.delay(node, next)?)));
+            let Some((next, delay)) = next else {
+                flow.dropped_no_route += 1;
+                continue;
+            };
+
+            let queue = self.links.entry((node, next)).or_default();
+            while let Some(&(sent, size)) = queue.queue.front() {
+                if sent > time {
+                    break;
+                }
+                queue.queue.pop_front();
+                queue.bytes -= size;
+            }
+            if queue.bytes + packet.size > self.queue_size {
+                flow.dropped_queue += 1;
+                continue;
+            }
+            // ground links share the ISL budget
+            let capacity = LinkBudget::new(link_budget, delay * network::SPEED_OF_LIGHT).capacity;
+            let sent = queue.busy_until.max(time) + packet.size as f64 * 8.0 / capacity;
+            queue.busy_until = sent;
+            queue.queue.push_back((sent, packet.size));
+            queue.bytes += packet.size;
+            self.arrivals.push(Arrival {
+                time: sent + delay,
+                node: next,
+                link: Some((node, next)),
+                packet: Packet {
+                    hops: packet.hops + 1,
+                    ..packet
+                },
+            });
+        }
+        // idle links
+        self.links.retain(|_, queue| queue.busy_until > now);
+    }
+}
+
+fn setup(mut commands: Commands, config: Res<Config>) -> Result {
+    commands.insert_resource(Traffic::new(&config.traffic)?);
+    Ok(())
+}
+
+/// Advance the traffic to the current simulation time.
+/// A jump of the clock, or running it backwards, discards the packets in flight.
+fn simulate_traffic(
+    clock: Res<SimulationClock>,
+    config: Res<Config>,
+    graph: Res<NetworkGraph>,
+    mut routing: ResMut<Routing>,
+    mut traffic: ResMut<Traffic>,
+    mut rng: ResMut<SimulationRng>,
+    names: Query<(Entity, &Name)>,
+) {
+    if traffic.flows.is_empty() {
+        return;
+    }
+    let now = clock.elapsed_secs();
+    if clock.jumps() != traffic.jumps || now < traffic.time {
+        traffic.jumps = clock.jumps();
+        traffic.reset(now, &mut **rng);
+        return;
+    }
+
+    if traffic.flows.iter().any(|flow| flow.endpoints.is_none()) {
+        let entities: HashMap<&str, Entity> = names
+            .iter()
+            .map(|(entity, name)| (name.as_str(), entity))
+            .collect();
+        for flow in traffic
+            .flows
+            .iter_mut()
+            .filter(|flow| flow.endpoints.is_none())
+        {
+            let from = entities.get(flow.config.from.as_str());
+            let to = entities.get(flow.config.to.as_str());
+            flow.endpoints = from.copied().zip(to.copied());
+        }
+    }
+
+    traffic.generate(now, &mut **rng);
+    traffic.forward(now, &graph, &config.link_budget, |node, destination| {
+        let path = routing.route(node, destination).first()?;
+        path.nodes.get(1).copied()
+    });
+    traffic.time = now;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let [a, b, c] = [0, 1, 2].map(Entity::from_raw);
+        let mut graph = NetworkGraph::default();
+        graph.link(a, b, 0.01);
+        graph.link(b, c, 0.01);
+        let link_budget = LinkBudgetConfig::default();
+        let capacity = LinkBudget::new(&link_budget, 0.01 * network::SPEED_OF_LIGHT).capacity;
+
+        let config = TrafficConfig {
+            queue_size: 3000,
+            flows: vec![FlowConfig {
+                name: "a-c".to_string(),
+                from: "a".to_string(),
+                to: "c".to_string(),
+                generator: TrafficGenerator::ConstantBitrate { bitrate: 1.2e6 },
+                packet_size: 1500,
+                start: 0.0,
+                stop: Some(1.0),
+            }],
+        };
+        let mut traffic = Traffic::new(&config).unwrap();
+        traffic.flows[0].endpoints = Some((a, c));
+        let mut rng = rand::rng();
+        let next_hop = |graph: &NetworkGraph, node, destination| {
+            graph
+                .shortest_path(node, destination)?
+                .nodes
+                .get(1)
+                .copied()
+        };
+
+        traffic.generate(2.0, &mut rng);
+        traffic.forward(2.0, &graph, &link_budget, |node, dest| {
+            next_hop(&graph, node, dest)
+        });
+        traffic.time = 2.0;
+        let stats = &traffic.stats()[0];
+        // one packet every 10 ms
+        assert_eq!(stats.generated, 100);
+        assert_eq!(stats.delivered, 100);
+        let expected = 2.0 * (0.01 + 1500.0 * 8.0 / capacity);
+        assert!((stats.mean_delay.unwrap() - expected).abs() < 1e-9);
+        assert!((stats.throughput - 1.2e6).abs() < 1e-6);
+
+        // packets on a link torn down are dropped
+        let mut traffic = Traffic::new(&config).unwrap();
+        traffic.flows[0].endpoints = Some((a, c));
+        traffic.generate(0.015, &mut rng);
+        traffic.forward(0.015, &graph, &link_budget, |node, dest| {
+            next_hop(&graph, node, dest)
+        });
+        let mut broken = NetworkGraph::default();
+        broken.link(b, c, 0.01);
+        traffic.forward(0.1, &broken, &link_budget, |node, dest| {
+            next_hop(&broken, node, dest)
+        });
+        traffic.time = 0.1;
+        let stats = &traffic.stats()[0];
+        assert_eq!(stats.delivered, 1);
+        assert_eq!(stats.dropped_link_down, 1);
+    }
+
+    #[test]
+    fn test_invalid_flows() {
+        let flow = |generator: TrafficGenerator, packet_size: usize| {
+            Flow::new(&FlowConfig {
+                name: "a-b".to_string(),
+                from: "a".to_string(),
+                to: "b".to_string(),
+                generator,
+                packet_size,
+                start: 0.0,
+                stop: None,
+            })
+        };
+        assert!(flow(TrafficGenerator::Poisson { rate: 10.0 }, 1500).is_ok());
+        assert!(flow(TrafficGenerator::ConstantBitrate { bitrate: 1e6 }, 1500).is_ok());
+
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(flow(TrafficGenerator::Poisson { rate }, 1500).is_err());
+        }
+        for bitrate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(flow(TrafficGenerator::ConstantBitrate { bitrate }, 1500).is_err());
+        }
+        // every packet would be sent at the same time
+        assert!(flow(TrafficGenerator::ConstantBitrate { bitrate: 1e6 }, 0).is_err());
+        assert!(flow(TrafficGenerator::Poisson { rate: 10.0 }, 0).is_err());
+    }
+
+    #[test]
+    fn test_ground_link_capacity() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        // a ground station 1000 km below a satellite
+        let mut world = World::new();
+        world.init_resource::<NetworkGraph>();
+        let satellite = world
+            .spawn((
+                Satellite { mean_anomaly: 0.0 },
+                Connections::default(),
+                Transform::from_xyz(0.0, EARTH_RADIUS + 1000.0, 0.0),
+            ))
+            .id();
+        let station = world
+            .spawn((
+                GroundLinks {
+                    satellites: vec![satellite],
+                },
+                Transform::from_xyz(0.0, EARTH_RADIUS, 0.0),
+            ))
+            .id();
+        world.run_system_once(rebuild_network_graph).unwrap();
+        let graph = world.resource::<NetworkGraph>();
+        let delay = graph.delay(station, satellite).unwrap();
+
+        let config = TrafficConfig {
+            queue_size: 3000,
+            flows: vec![FlowConfig {
+                name: "ground".to_string(),
+                from: "station".to_string(),
+                to: "satellite".to_string(),
+                generator: TrafficGenerator::ConstantBitrate { bitrate: 1.2e6 },
+                packet_size: 1500,
+                start: 0.0,
+                stop: Some(0.005),
+            }],
+        };
+        let link_budget = LinkBudgetConfig::default();
+        let mut traffic = Traffic::new(&config).unwrap();
+        traffic.flows[0].endpoints = Some((station, satellite));
+        traffic.generate(1.0, &mut rand::rng());
+        traffic.forward(1.0, graph, &link_budget, |_, destination| Some(destination));
+        traffic.time = 1.0;
+
+        // serialized at the capacity of an ISL as long
+        let capacity = LinkBudget::new(&link_budget, 1000.0).capacity;
+        let stats = &traffic.stats()[0];
+        assert_eq!(stats.delivered, 1);
+        let expected = delay + 1500.0 * 8.0 / capacity;
+        assert!((stats.mean_delay.unwrap() - expected).abs() < 1e-9);
+    }
+}
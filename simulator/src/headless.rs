@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::{ScheduleRunnerPlugin, TerminalCtrlCHandlerPlugin},
+    log::LogPlugin,
+    time::TimeUpdateStrategy,
+};
+
+use crate::prelude::*;
+
+/// Marks an app running without a window or a renderer.
+/// Plugins check it while building, to leave out their display systems.
+#[derive(Resource)]
+pub struct Headless;
+
+/// Run the simulation without a window, for batch runs on servers.
+pub struct HeadlessPlugin {
+    /// Exit after this many seconds of simulation time.
+    pub duration: Option<f64>,
+    /// Pace the fixed steps by the wall clock, instead of running as fast as possible.
+    pub realtime: bool,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Headless);
+
+        // one fixed step per update
+        let timestep = Time::<Fixed>::default().timestep();
+        let wait = if self.realtime {
+            timestep
+        } else {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+            Duration::ZERO
+        };
+        app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(wait)),
+            LogPlugin::default(),
+            // exit on Ctrl-C through `AppExit`, so the recorder can close its files
+            TerminalCtrlCHandlerPlugin,
+            TransformPlugin,
+            AssetPlugin::default(),
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>();
+
+        if let Some(duration) = self.duration {
+            let start = Instant::now();
+            app.add_systems(
+                Update,
+                move |clock: Res<SimulationClock>, mut exit: EventWriter<AppExit>| {
+                    if clock.elapsed_secs() >= duration {
+                        info!(
+                            "Simulated {} s in {:.1} s, exiting",
+                            duration,
+                            start.elapsed().as_secs_f64()
+                        );
+                        exit.write(AppExit::Success);
+                    }
+                },
+            );
+        }
+    }
+}
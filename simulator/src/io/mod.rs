@@ -1,9 +1,12 @@
 use crate::prelude::*;
-use brp_packages::AddSatellite;
+use brp_packages::{AddOrbit, AddSatellite, AttachSatellite, FieldError};
 use serde::{Deserialize, Serialize};
 
+use bevy::ecs::{entity::EntityHashMap, relationship::Relationship, system::SystemParam};
+use bevy::math::DVec3;
 use bevy::remote::{error_codes, http::RemoteHttpPlugin, BrpError, BrpResult, RemotePlugin};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 
 pub struct IOPlugin {
     port: u16,
@@ -21,7 +24,29 @@ impl Plugin for IOPlugin {
 
         let remote_plugin = RemotePlugin::default()
             .with_method("add_satellite", add_satellite)
-            .with_method("add_satellites", add_satellites);
+            .with_method("add_satellites", add_satellites)
+            .with_method("add_orbits", add_orbits)
+            .with_method("attach_satellites", attach_satellites)
+            .with_method("get_clock", get_clock)
+            .with_method("get_ephemeris", get_ephemeris)
+            .with_method("set_clock", set_clock)
+            .with_method("get_link_budgets", get_link_budgets)
+            .with_method("get_latency_path", get_latency_path)
+            .with_method("get_route", get_route)
+            .with_method("set_routing", set_routing)
+            .with_method("get_traffic_stats", get_traffic_stats)
+            .with_method("get_compute_stats", get_compute_stats)
+            .with_method("set_offload_policy", set_offload_policy)
+            .with_method("get_pending_tasks", get_pending_tasks)
+            .with_method("assign_task", assign_task)
+            .with_method("get_power", get_power)
+            .with_method("get_link_stats", get_link_stats)
+            .with_method("get_link_events", get_link_events)
+            .with_method("get_topology", get_topology)
+            .with_method("get_satellites", get_satellites)
+            .with_method("get_satellite", get_satellite)
+            .with_method("remove_satellites", remove_satellites)
+            .with_method("remove_orbits", remove_orbits);
 
         app.add_plugins((remote_plugin, remote_http_plugin));
     }
@@ -48,61 +73,1412 @@ fn parse_some<T: for<'de> Deserialize<'de>>(value: Option<Value>) -> Result<T, B
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AddSatelliteParams {
-    id: String,
-    elements: [f32; 6],
+/// An `INVALID_PARAMS` error listing the fields that failed, and why, as its data.
+fn invalid_fields(errors: Vec<FieldError>) -> BrpError {
+    let fields: Vec<String> = errors
+        .iter()
+        .map(|error| format!("{} {}", error.field, error.reason))
+        .collect();
+    BrpError {
+        code: error_codes::INVALID_PARAMS,
+        message: format!("Invalid params: {}", fields.join("; ")),
+        data: Some(serde_json::json!({ "fields": errors })),
+    }
+}
+
+/// Spawns satellites, whose IDs must not be in use by entities or satellites still to be
+/// spawned.
+#[derive(SystemParam)]
+struct NewSatellites<'w, 's> {
+    names: Query<'w, 's, &'static Name>,
+    spawning: ResMut<'w, Events<SpawnSatellites>>,
+    attaching: ResMut<'w, Events<AttachSatellites>>,
+}
+
+impl NewSatellites<'_, '_> {
+    /// Check that new IDs, by the field they are in, are neither empty nor taken, also by
+    /// each other.
+    fn check_ids<'a>(
+        &'a self,
+        ids: impl IntoIterator<Item = (String, &'a str)>,
+    ) -> Vec<FieldError> {
+        let (mut spawning, mut attaching) =
+            (self.spawning.get_cursor(), self.attaching.get_cursor());
+        let spawning = spawning
+            .read(&self.spawning)
+            .flat_map(|event| event.satellites.iter().map(|(id, _)| id.as_str()));
+        let attaching = attaching
+            .read(&self.attaching)
+            .flat_map(|event| event.satellites.iter().map(|(_, id, _)| id.as_str()));
+        let mut taken: HashSet<&str> = self
+            .names
+            .iter()
+            .map(Name::as_str)
+            .chain(spawning)
+            .chain(attaching)
+            .collect();
+        ids.into_iter()
+            .filter_map(|(field, id)| {
+                if id.trim().is_empty() {
+                    Some(FieldError::new(field, "must not be empty"))
+                } else if !taken.insert(id) {
+                    Some(FieldError::new(field, format!("{} is already in use", id)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn spawn(&mut self, satellites: Vec<(String, OrbitalElements)>) {
+        self.spawning.send(SpawnSatellites { satellites });
+    }
+
+    fn attach(&mut self, satellites: Vec<(Entity, String, Satellite)>) {
+        self.attaching.send(AttachSatellites { satellites });
+    }
 }
 
-/// Add a satellite.
+/// Add a satellite on its own orbit.
 ///
 /// # Parameters
-/// - ID: String - The ID of the satellite.
-/// - orbitial elements: [Number, .. ] - The data of the satellite.
+/// - id: String - The ID of the satellite, unique among satellites and ground stations.
+/// - apogee, perigee: Number - Radii of the apsides, or
+/// - mean_motion, eccentricity: Number - instead.
+/// - inclination, longitude_of_ascending_node, argument_of_periapsis, mean_anomaly: Number
+/// - units: { angle: "deg" | "rad", distance: "km" | "m", mean_motion: "rev/day" | "rad/s" } -
+///   Optional, rad, km and rad/s by default.
+///
+/// Fields out of range are listed in the error data, as `{ fields: [ { field, reason }, .. ] }`.
 fn add_satellite(
     In(params): In<Option<Value>>,
-    mut event: EventWriter<SpawnSatellites>,
+    mut new_satellites: NewSatellites,
 ) -> BrpResult<Value> {
     let satellite: AddSatellite = parse_some(params)?;
 
-    let data = OrbitalElements::from_slice(&satellite.as_slice()).map_err(|err| BrpError {
-        code: error_codes::INVALID_PARAMS,
-        message: err,
-        data: None,
+    let mut errors = new_satellites.check_ids([("id".to_string(), satellite.id.as_str())]);
+    let elements = satellite.validate().map_err(|fields| {
+        errors.extend(fields);
+        invalid_fields(errors.clone())
     })?;
-    event.write(SpawnSatellites {
-        satellites: vec![("id".to_string(), data)],
-    });
+    if !errors.is_empty() {
+        return Err(invalid_fields(errors));
+    }
+    let data = OrbitalElements::from_slice(&elements)
+        .map_err(|err| invalid_fields(vec![FieldError::new("eccentricity", err)]))?;
+    new_satellites.spawn(vec![(satellite.id, data)]);
 
     BrpResult::Ok(Value::Null)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize)]
 struct AddSatellitesParams {
-    satellites: Vec<AddSatelliteParams>,
+    satellites: Vec<AddSatellite>,
 }
 
 /// Add multiple satellites.
 ///
 /// # Parameters
-/// - satellites: [ { id: String, elements: [Number, .. ] }, .. ] - The data of the satellites.
+/// - satellites: [ { id: String, .. }, .. ] - The satellites, each in the form of `add_satellite`.
+///   IDs must be unique.
+///
+/// Fields out of range are listed in the error data, e.g. `satellites[2].inclination`.
 fn add_satellites(
     In(params): In<Option<Value>>,
-    mut event: EventWriter<SpawnSatellites>,
+    mut new_satellites: NewSatellites,
 ) -> BrpResult<Value> {
     let AddSatellitesParams { satellites } = parse_some(params)?;
 
-    let satellites = satellites
+    let mut errors = new_satellites.check_ids(
+        satellites
+            .iter()
+            .enumerate()
+            .map(|(i, satellite)| (format!("satellites[{}].id", i), satellite.id.as_str())),
+    );
+    let mut data = vec![];
+    for (i, satellite) in satellites.into_iter().enumerate() {
+        let parent = format!("satellites[{}]", i);
+        let elements = match satellite.validate() {
+            Ok(elements) => elements,
+            Err(fields) => {
+                errors.extend(fields.into_iter().map(|error| error.within(&parent)));
+                continue;
+            }
+        };
+        match OrbitalElements::from_slice(&elements) {
+            Ok(elements) => data.push((satellite.id, elements)),
+            Err(err) => errors.push(FieldError::new("eccentricity", err).within(&parent)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(invalid_fields(errors));
+    }
+    new_satellites.spawn(data);
+    BrpResult::Ok(Value::Null)
+}
+
+#[derive(Debug, Deserialize)]
+struct AddOrbitsParams {
+    orbits: Vec<AddOrbit>,
+}
+
+/// Add orbits without satellites, to attach satellites to with `attach_satellites`.
+///
+/// # Parameters
+/// - orbits: [ { apogee: Number, perigee: Number, inclination: Number,
+///   longitude_of_ascending_node: Number, argument_of_periapsis: Number, units: { .. } }, .. ] -
+///   As in `add_satellite`, without the ID and mean anomaly.
+///
+/// # Returns
+/// The IDs of the orbits, in the same order. They stay valid until the orbits are removed.
+fn add_orbits(
+    In(params): In<Option<Value>>,
+    mut commands: Commands,
+    mut events: EventWriter<SpawnOrbits>,
+) -> BrpResult<Value> {
+    let AddOrbitsParams { orbits } = parse_some(params)?;
+
+    let mut errors = vec![];
+    let mut valid = vec![];
+    for (i, orbit) in orbits.iter().enumerate() {
+        let parent = format!("orbits[{}]", i);
+        match orbit.validate() {
+            Ok(elements) => match Orbit::from_slice(&elements) {
+                Ok(orbit) => valid.push(orbit),
+                Err(err) => errors.push(FieldError::new("eccentricity", err).within(&parent)),
+            },
+            Err(fields) => errors.extend(fields.into_iter().map(|error| error.within(&parent))),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(invalid_fields(errors));
+    }
+    // reserve the entities now, so the IDs are known before the orbits are spawned
+    let orbits: Vec<(Entity, Orbit)> = valid
+        .into_iter()
+        .map(|orbit| (commands.spawn_empty().id(), orbit))
+        .collect();
+    let ids: Vec<u64> = orbits.iter().map(|(entity, _)| entity.to_bits()).collect();
+    events.write(SpawnOrbits { orbits });
+    serde_json::to_value(ids).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachSatellitesParams {
+    orbit: u64,
+    satellites: Vec<AttachSatellite>,
+}
+
+/// Add satellites on an existing orbit, e.g. the satellites of one plane.
+///
+/// # Parameters
+/// - orbit: Number - The ID of the orbit, as returned by `add_orbits`.
+/// - satellites: [ { id: String, mean_anomaly: Number, units: { angle: "deg" | "rad" } }, .. ] -
+///   Mean anomalies in rad by default. IDs must be unique.
+fn attach_satellites(
+    In(params): In<Option<Value>>,
+    orbits: Query<(), With<Orbit>>,
+    spawning: Res<Events<SpawnOrbits>>,
+    mut new_satellites: NewSatellites,
+) -> BrpResult<Value> {
+    let AttachSatellitesParams { orbit, satellites } = parse_some(params)?;
+
+    // orbits added by `add_orbits` are spawned at the next fixed step
+    let is_spawning = |entity: Entity| {
+        spawning
+            .get_cursor()
+            .read(&spawning)
+            .any(|event| event.orbits.iter().any(|(orbit, _)| *orbit == entity))
+    };
+    let orbit_entity = Entity::try_from_bits(orbit)
+        .ok()
+        .filter(|entity| orbits.contains(*entity) || is_spawning(*entity));
+
+    let mut errors = new_satellites.check_ids(
+        satellites
+            .iter()
+            .enumerate()
+            .map(|(i, satellite)| (format!("satellites[{}].id", i), satellite.id.as_str())),
+    );
+    if orbit_entity.is_none() {
+        errors.insert(0, FieldError::new("orbit", format!("{} not found", orbit)));
+    }
+    let mut data = vec![];
+    for (i, satellite) in satellites.into_iter().enumerate() {
+        match satellite.validate() {
+            Ok(mean_anomaly) => data.push((satellite.id, Satellite { mean_anomaly })),
+            Err(fields) => {
+                let parent = format!("satellites[{}]", i);
+                errors.extend(fields.into_iter().map(|error| error.within(&parent)));
+            }
+        }
+    }
+    let Some(orbit_entity) = orbit_entity.filter(|_| errors.is_empty()) else {
+        return Err(invalid_fields(errors));
+    };
+
+    new_satellites.attach(
+        data.into_iter()
+            .map(|(id, satellite)| (orbit_entity, id, satellite))
+            .collect(),
+    );
+    BrpResult::Ok(Value::Null)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClockState {
+    time: String,
+    epoch: String,
+    elapsed: f64,
+    time_speed: f64,
+    paused: bool,
+}
+
+impl ClockState {
+    fn new(clock: &SimulationClock) -> Self {
+        Self {
+            time: clock.now().to_rfc3339(),
+            epoch: clock.epoch().to_rfc3339(),
+            elapsed: clock.elapsed_secs(),
+            time_speed: clock.time_speed(),
+            paused: clock.is_paused(),
+        }
+    }
+}
+
+/// Get the state of the simulation clock.
+///
+/// # Returns
+/// - { time: String, epoch: String, elapsed: Number, time_speed: Number, paused: Boolean }
+fn get_clock(In(_params): In<Option<Value>>, clock: Res<SimulationClock>) -> BrpResult<Value> {
+    serde_json::to_value(ClockState::new(&clock)).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Serialize)]
+struct EphemerisInfo {
+    time: String,
+    sun: DVec3,  // (km)
+    moon: DVec3, // (km)
+}
+
+/// Get the positions of the Sun and the Moon at the current clock time.
+///
+/// # Returns
+/// - { time: String, sun: [Number; 3], moon: [Number; 3] }
+///   Positions in km, in the ECI frame of the scene.
+fn get_ephemeris(
+    In(_params): In<Option<Value>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+) -> BrpResult<Value> {
+    let info = EphemerisInfo {
+        time: clock.now().to_rfc3339(),
+        sun: ephemeris.sun,
+        moon: ephemeris.moon,
+    };
+    serde_json::to_value(info).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SetClockParams {
+    paused: Option<bool>,
+    time_speed: Option<f64>,
+    time: Option<String>,
+    #[serde(default)]
+    step: bool,
+}
+
+/// Control the simulation clock. All parameters are optional.
+///
+/// # Parameters
+/// - paused: Boolean - Pause or resume the simulation.
+/// - time_speed: Number - Simulated seconds per real second.
+/// - time: String - Jump to the UTC time, e.g. "2024-10-27T04:10:58".
+/// - step: Boolean - Advance a single tick while paused.
+///
+/// # Returns
+/// The state of the clock, the same as `get_clock`.
+fn set_clock(
+    In(params): In<Option<Value>>,
+    mut clock: ResMut<SimulationClock>,
+) -> BrpResult<Value> {
+    let params: SetClockParams = parse_some(params)?;
+
+    let time = params
+        .time
+        .as_deref()
+        .map(parse_time_from_str)
+        .transpose()
+        .map_err(|err| BrpError {
+            code: error_codes::INVALID_PARAMS,
+            message: err,
+            data: None,
+        })?;
+    if let Some(time_speed) = params.time_speed {
+        clock.set_time_speed(time_speed);
+    }
+    if let Some(paused) = params.paused {
+        clock.set_paused(paused);
+    }
+    if let Some(time) = time {
+        clock.jump_to(time);
+    }
+    if params.step {
+        clock.step();
+    }
+
+    serde_json::to_value(ClockState::new(&clock)).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Serialize)]
+struct LinkBudgetInfo<'a> {
+    from: &'a str,
+    to: &'a str,
+    #[serde(flatten)]
+    budget: LinkBudget,
+}
+
+/// Get the budgets of all inter-satellite links, each link once.
+///
+/// # Returns
+/// - [ { from: String, to: String, distance: Number, path_loss: Number, snr: Number, capacity: Number, delay: Number }, .. ]
+///   Distance in km, path loss and SNR in dB, capacity in bit/s, one-way delay in s.
+fn get_link_budgets(
+    In(_params): In<Option<Value>>,
+    satellites: Query<(Entity, &Name, &LinkBudgets)>,
+    names: Query<&Name>,
+) -> BrpResult<Value> {
+    let mut links = vec![];
+    for (sat, name, budgets) in &satellites {
+        for (&other, &budget) in &budgets.0 {
+            // each link once
+            if sat > other {
+                continue;
+            }
+            let Ok(other_name) = names.get(other) else {
+                continue;
+            };
+            links.push(LinkBudgetInfo {
+                from: name.as_str(),
+                to: other_name.as_str(),
+                budget,
+            });
+        }
+    }
+    serde_json::to_value(links).map_err(BrpError::internal)
+}
+
+/// Find an entity (satellite or ground station) by its name.
+fn find_by_name(names: &Query<(Entity, &Name)>, name: &str) -> Result<Entity, BrpError> {
+    names
+        .iter()
+        .find(|(_, n)| n.as_str() == name)
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| BrpError {
+            code: error_codes::INVALID_PARAMS,
+            message: format!("{} not found", name),
+            data: None,
+        })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PathParams {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PathInfo<'a> {
+    path: Vec<&'a str>,
+    delay: f64,
+}
+
+impl<'a> PathInfo<'a> {
+    fn new(path: &Path, names: &'a Query<(Entity, &Name)>) -> Self {
+        Self {
+            path: path
+                .nodes
+                .iter()
+                .filter_map(|node| names.get(*node).ok().map(|(_, name)| name.as_str()))
+                .collect(),
+            delay: path.delay,
+        }
+    }
+}
+
+/// Get the minimum-latency path between two satellites or ground stations
+/// at the current simulation time.
+///
+/// # Parameters
+/// - from: String - The ID of the satellite or the name of the ground station.
+/// - to: String - The same as `from`.
+///
+/// # Returns
+/// - { path: [String, .. ], delay: Number } - The nodes along the path and the total one-way
+///   delay in s, or null if they are not connected.
+fn get_latency_path(
+    In(params): In<Option<Value>>,
+    graph: Res<NetworkGraph>,
+    names: Query<(Entity, &Name)>,
+) -> BrpResult<Value> {
+    let PathParams { from, to } = parse_some(params)?;
+    let from = find_by_name(&names, &from)?;
+    let to = find_by_name(&names, &to)?;
+
+    let Some(path) = graph.shortest_path(from, to) else {
+        return Ok(Value::Null);
+    };
+    serde_json::to_value(PathInfo::new(&path, &names)).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Serialize)]
+struct RouteInfo<'a> {
+    algorithm: String,
+    paths: Vec<PathInfo<'a>>,
+}
+
+/// Look up the routing table.
+///
+/// # Parameters
+/// - from: String - The ID of the satellite or the name of the ground station.
+/// - to: String - The same as `from`.
+///
+/// # Returns
+/// - { algorithm: String, paths: [ { path: [String, .. ], delay: Number }, .. ] } - The best
+///   path first, empty if they are not connected.
+fn get_route(
+    In(params): In<Option<Value>>,
+    mut routing: ResMut<Routing>,
+    names: Query<(Entity, &Name)>,
+) -> BrpResult<Value> {
+    let PathParams { from, to } = parse_some(params)?;
+    let from = find_by_name(&names, &from)?;
+    let to = find_by_name(&names, &to)?;
+
+    let algorithm = routing.algorithm();
+    let paths = routing
+        .route(from, to)
+        .iter()
+        .map(|path| PathInfo::new(path, &names))
+        .collect();
+    serde_json::to_value(RouteInfo { algorithm, paths }).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct SetRoutingParams {
+    algorithm: Option<RoutingAlgorithmKind>,
+    k: Option<usize>,
+    interval: Option<f64>,
+}
+
+/// Switch the routing algorithm or the update interval. All parameters are optional.
+///
+/// # Parameters
+/// - algorithm: String - "hop_count", "latency" or "k_shortest_paths".
+/// - k: Number - Number of paths of "k_shortest_paths", defaults to the config.
+///   Only with `algorithm: "k_shortest_paths"`, as the current algorithm is replaced.
+/// - interval: Number - Interval (s of simulation time) between routing table updates.
+fn set_routing(
+    In(params): In<Option<Value>>,
+    config: Res<Config>,
+    mut routing: ResMut<Routing>,
+) -> BrpResult<Value> {
+    let params: SetRoutingParams = parse_some(params)?;
+    if params.k.is_some() && params.algorithm != Some(RoutingAlgorithmKind::KShortestPaths) {
+        return Err(invalid_fields(vec![FieldError::new(
+            "k",
+            "requires algorithm \"k_shortest_paths\"",
+        )]));
+    }
+    if let Some(algorithm) = params.algorithm {
+        routing.set_algorithm_from_config(algorithm, params.k.unwrap_or(config.routing.k));
+    }
+    if let Some(interval) = params.interval {
+        routing.set_interval(interval);
+    }
+    BrpResult::Ok(Value::Null)
+}
+
+/// Get the statistics of every traffic flow.
+///
+/// # Returns
+/// - [ { name: String, generated: Number, delivered: Number, dropped_queue: Number,
+///   dropped_no_route: Number, dropped_link_down: Number, throughput: Number,
+///   mean_delay: Number, max_delay: Number, loss_rate: Number }, .. ]
+///   Throughput in bit/s, delays in s (null before the first delivered packet).
+fn get_traffic_stats(In(_params): In<Option<Value>>, traffic: Res<Traffic>) -> BrpResult<Value> {
+    serde_json::to_value(traffic.stats()).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Serialize)]
+struct ComputeStatsInfo {
+    policy: String,
+    #[serde(flatten)]
+    stats: ComputeStats,
+}
+
+/// Get the statistics of computation tasks.
+///
+/// # Returns
+/// - { policy: String, generated: Number, completed: Number, deadline_missed: Number,
+///   dropped: Number, mean_latency: Number }
+///   Mean latency from generation to completion in s, null before the first completed task.
+fn get_compute_stats(
+    In(_params): In<Option<Value>>,
+    offloading: Res<Offloading>,
+) -> BrpResult<Value> {
+    let info = ComputeStatsInfo {
+        policy: offloading.policy(),
+        stats: offloading.stats(),
+    };
+    serde_json::to_value(info).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct SetOffloadPolicyParams {
+    policy: OffloadPolicyKind,
+    k: Option<usize>,
+}
+
+/// Switch the offloading policy of new tasks.
+///
+/// # Parameters
+/// - policy: String - "local", "nearest_neighbour", "least_loaded" or "external".
+/// - k: Number - Optional, number of hops searched by "least_loaded", defaults to the config.
+///   Only with `policy: "least_loaded"`.
+fn set_offload_policy(
+    In(params): In<Option<Value>>,
+    config: Res<Config>,
+    mut offloading: ResMut<Offloading>,
+) -> BrpResult<Value> {
+    let SetOffloadPolicyParams { policy, k } = parse_some(params)?;
+    if k.is_some() && policy != OffloadPolicyKind::LeastLoaded {
+        return Err(invalid_fields(vec![FieldError::new(
+            "k",
+            "requires policy \"least_loaded\"",
+        )]));
+    }
+    offloading.set_policy_from_config(policy, k.unwrap_or(config.compute.k));
+    BrpResult::Ok(Value::Null)
+}
+
+#[derive(Debug, Serialize)]
+struct PendingTaskInfo<'a> {
+    task: u64,
+    source: &'a str,
+    origin: &'a str,
+    input_size: f64,
+    cycles: f64,
+    created: f64,
+    deadline: f64,
+}
+
+/// Get the tasks waiting for a decision of the "external" policy.
+///
+/// # Returns
+/// - [ { task: Number, source: String, origin: String, input_size: Number, cycles: Number,
+///   created: Number, deadline: Number }, .. ]
+///   The ID of the task, the satellite or ground station that generated it, the satellite it
+///   starts from, input size in bytes, CPU cycles, and simulation times in s.
+fn get_pending_tasks(
+    In(_params): In<Option<Value>>,
+    tasks: Query<(Entity, &Task, &TaskState)>,
+    names: Query<&Name>,
+) -> BrpResult<Value> {
+    let name = |entity| {
+        names
+            .get(entity)
+            .map(|name| name.as_str())
+            .unwrap_or_default()
+    };
+    let pending: Vec<_> = tasks
+        .iter()
+        .filter(|(_, _, state)| **state == TaskState::Pending)
+        .map(|(entity, task, _)| PendingTaskInfo {
+            task: entity.to_bits(),
+            source: name(task.source),
+            origin: name(task.origin),
+            input_size: task.input_size,
+            cycles: task.cycles,
+            created: task.created,
+            deadline: task.deadline,
+        })
+        .collect();
+    serde_json::to_value(pending).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignTaskParams {
+    task: u64,
+    node: String,
+}
+
+/// Run a pending task on a satellite. The task is sent along the current route.
+///
+/// # Parameters
+/// - task: Number - The ID from `get_pending_tasks`.
+/// - node: String - The ID of the satellite.
+fn assign_task(
+    In(params): In<Option<Value>>,
+    clock: Res<SimulationClock>,
+    config: Res<Config>,
+    graph: Res<NetworkGraph>,
+    mut routing: ResMut<Routing>,
+    mut tasks: Query<(&Task, &mut TaskState)>,
+    names: Query<(Entity, &Name)>,
+) -> BrpResult<Value> {
+    let AssignTaskParams { task, node } = parse_some(params)?;
+    let invalid = |message: String| BrpError {
+        code: error_codes::INVALID_PARAMS,
+        message,
+        data: None,
+    };
+    let node = find_by_name(&names, &node)?;
+    let (task, mut state) = Entity::try_from_bits(task)
+        .ok()
+        .and_then(|entity| tasks.get_mut(entity).ok())
+        .filter(|(_, state)| **state == TaskState::Pending)
+        .ok_or_else(|| invalid(format!("Task {} is not pending", task)))?;
+
+    *state = dispatch_task(
+        task,
+        node,
+        clock.elapsed_secs(),
+        &mut routing,
+        &graph,
+        &config.link_budget,
+    )
+    .ok_or_else(|| invalid("No route to the node".to_string()))?;
+    BrpResult::Ok(Value::Null)
+}
+
+#[derive(Debug, Serialize)]
+struct PowerInfo<'a> {
+    id: &'a str,
+    state_of_charge: f64,
+    #[serde(flatten)]
+    power: &'a PowerSystem,
+}
+
+/// Get the power system of every satellite.
+///
+/// # Returns
+/// - [ { id: String, state_of_charge: Number, capacity: Number, charge: Number,
+///   solar_power: Number, base_load: Number, consumption: Number, in_eclipse: Boolean }, .. ]
+///   State of charge from 0 to 1, energy in Wh, power in W.
+fn get_power(
+    In(_params): In<Option<Value>>,
+    satellites: Query<(&Name, &PowerSystem)>,
+) -> BrpResult<Value> {
+    let satellites: Vec<_> = satellites
+        .iter()
+        .map(|(name, power)| PowerInfo {
+            id: name.as_str(),
+            state_of_charge: power.state_of_charge(),
+            power,
+        })
+        .collect();
+    serde_json::to_value(satellites).map_err(BrpError::internal)
+}
+
+/// Get the churn statistics of ISLs and ground links, since the start or the last clock jump.
+///
+/// # Returns
+/// - { active_isls: Number, active_ground_links: Number, established: Number,
+///   torn_down: Number, mean_duration: Number, established_per_second: Number,
+///   torn_down_per_second: Number, reasons: { String: Number }, degree_histogram: [Number, .. ] }
+///   Mean duration of the links torn down in s, null before the first one.
+///   Teardowns by reason: "out_of_range", "occluded", "capacity", "polar_region", "low_power"
+///   or "manual". The number of satellites with 0, 1, 2.. ISLs.
+fn get_link_stats(
+    In(_params): In<Option<Value>>,
+    clock: Res<SimulationClock>,
+    log: Res<LinkLog>,
+    satellites: Query<Entity, With<Satellite>>,
+) -> BrpResult<Value> {
+    let stats = log.stats(clock.elapsed_secs(), satellites.iter());
+    serde_json::to_value(stats).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LinkEventsParams {
+    since: Option<f64>,
+    limit: Option<usize>,
+}
+
+/// Get the recent events of links established and torn down, oldest first.
+///
+/// # Parameters
+/// - since: Number - Optional, only the events after this simulation time in s.
+/// - limit: Number - Optional, only the latest `limit` events.
+///
+/// # Returns
+/// - [ { time: Number, a: String, b: String, kind: String, reason: String, lifetime: Number },
+///   .. ]
+///   `kind` is "isl" or "ground". `reason` and `lifetime` (s) are null when established.
+fn get_link_events(In(params): In<Option<Value>>, log: Res<LinkLog>) -> BrpResult<Value> {
+    let LinkEventsParams { since, limit } = match params {
+        Some(params) => parse(params)?,
+        None => default(),
+    };
+    let events: Vec<_> = log
+        .recent()
+        .filter(|event| since.is_none_or(|since| event.time > since))
+        .collect();
+    let skip = limit.map_or(0, |limit| events.len().saturating_sub(limit));
+    serde_json::to_value(&events[skip..]).map_err(BrpError::internal)
+}
+
+/// Layout of the topology returned by `get_topology`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TopologyFormat {
+    #[default]
+    Adjacency,
+    Edges,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TopologyParams {
+    format: TopologyFormat,
+    distances: bool,
+    positions: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AdjacencyInfo<'a> {
+    id: &'a str,
+    neighbours: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distances: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<Vec3>,
+}
+
+#[derive(Debug, Serialize)]
+struct EdgeListInfo<'a> {
+    nodes: Vec<&'a str>,
+    edges: Vec<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distances: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    positions: Option<Vec<Vec3>>,
+}
+
+/// Get the current ISL topology.
+///
+/// # Parameters
+/// - format: String - Optional, "adjacency" (default) or "edges".
+/// - distances: Boolean - Optional, include the length of every link in km.
+/// - positions: Boolean - Optional, include the position of every satellite in km (scene frame).
+///
+/// # Returns
+/// - "adjacency": [ { id: String, neighbours: [String, .. ], distances: [Number, .. ],
+///   position: [Number, Number, Number] }, .. ]
+/// - "edges": { nodes: [String, .. ], edges: [[Number, Number], .. ], distances: [Number, .. ],
+///   positions: [[Number, Number, Number], .. ] }
+///   Every link once, by the indices of its satellites in `nodes`.
+fn get_topology(
+    In(params): In<Option<Value>>,
+    satellites: Query<(Entity, &Name, &Connections, &Transform), With<Satellite>>,
+) -> BrpResult<Value> {
+    let params: TopologyParams = match params {
+        Some(params) => parse(params)?,
+        None => default(),
+    };
+
+    let indices: EntityHashMap<usize> = satellites
+        .iter()
+        .enumerate()
+        .map(|(i, (sat, ..))| (sat, i))
+        .collect();
+    let nodes: Vec<TopologyNode> = satellites
+        .iter()
+        .map(|(_, name, conns, transform)| TopologyNode {
+            id: name.as_str(),
+            position: transform.translation,
+            neighbours: conns
+                .satellites()
+                .iter()
+                .filter_map(|other| indices.get(other).copied())
+                .collect(),
+        })
+        .collect();
+
+    match params.format {
+        TopologyFormat::Adjacency => {
+            serde_json::to_value(adjacency(&nodes, &params)).map_err(BrpError::internal)
+        }
+        TopologyFormat::Edges => {
+            serde_json::to_value(edge_list(&nodes, &params)).map_err(BrpError::internal)
+        }
+    }
+}
+
+/// A satellite in the topology, with its neighbours by index
+struct TopologyNode<'a> {
+    id: &'a str,
+    position: Vec3,
+    neighbours: Vec<usize>,
+}
+
+impl TopologyNode<'_> {
+    fn distance(&self, other: &TopologyNode) -> f32 {
+        self.position.distance(other.position)
+    }
+}
+
+fn adjacency<'a>(nodes: &[TopologyNode<'a>], params: &TopologyParams) -> Vec<AdjacencyInfo<'a>> {
+    nodes
+        .iter()
+        .map(|node| AdjacencyInfo {
+            id: node.id,
+            neighbours: node.neighbours.iter().map(|&j| nodes[j].id).collect(),
+            distances: params.distances.then(|| {
+                node.neighbours
+                    .iter()
+                    .map(|&j| node.distance(&nodes[j]))
+                    .collect()
+            }),
+            position: params.positions.then_some(node.position),
+        })
+        .collect()
+}
+
+fn edge_list<'a>(nodes: &[TopologyNode<'a>], params: &TopologyParams) -> EdgeListInfo<'a> {
+    let mut edges = vec![];
+    let mut distances = vec![];
+    for (i, node) in nodes.iter().enumerate() {
+        // each link once
+        for &j in node.neighbours.iter().filter(|&&j| i < j) {
+            edges.push([i, j]);
+            if params.distances {
+                distances.push(node.distance(&nodes[j]));
+            }
+        }
+    }
+    EdgeListInfo {
+        nodes: nodes.iter().map(|node| node.id).collect(),
+        edges,
+        distances: params.distances.then_some(distances),
+        positions: params
+            .positions
+            .then(|| nodes.iter().map(|node| node.position).collect()),
+    }
+}
+
+/// A region of geodetic coordinates, in degrees and km. Bounds not given are open.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Region {
+    min_latitude: Option<f64>,
+    max_latitude: Option<f64>,
+    /// The region wraps across the antimeridian when `min_longitude > max_longitude`.
+    min_longitude: Option<f64>,
+    max_longitude: Option<f64>,
+    min_altitude: Option<f64>,
+    max_altitude: Option<f64>,
+}
+
+impl Region {
+    fn contains(&self, geodetic: &Geodetic) -> bool {
+        let (latitude, longitude) = (
+            geodetic.latitude.to_degrees(),
+            geodetic.longitude.to_degrees(),
+        );
+        let above = |value: f64, min: Option<f64>| min.is_none_or(|min| value >= min);
+        let below = |value: f64, max: Option<f64>| max.is_none_or(|max| value <= max);
+        let in_longitude = match (self.min_longitude, self.max_longitude) {
+            (Some(min), Some(max)) if min > max => longitude >= min || longitude <= max,
+            (min, max) => above(longitude, min) && below(longitude, max),
+        };
+        above(latitude, self.min_latitude)
+            && below(latitude, self.max_latitude)
+            && in_longitude
+            && above(geodetic.altitude, self.min_altitude)
+            && below(geodetic.altitude, self.max_altitude)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SatellitesParams {
+    ids: Option<Vec<String>>,
+    prefix: Option<String>,
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+struct StateInfo {
+    position: DVec3, // (km)
+    velocity: DVec3, // (km/s)
+}
+
+#[derive(Debug, Serialize)]
+struct GeodeticInfo {
+    latitude: f64,  // (degrees)
+    longitude: f64, // (degrees)
+    altitude: f64,  // (km)
+}
+
+/// Orbital elements in the units of OMM records.
+#[derive(Debug, Serialize)]
+struct ElementsInfo {
+    mean_motion: f64, // (rev/day)
+    eccentricity: f64,
+    inclination: f64,       // (degrees)
+    ra_of_asc_node: f64,    // (degrees)
+    arg_of_pericenter: f64, // (degrees)
+    mean_anomaly: f64,      // (degrees)
+}
+
+#[derive(Debug, Serialize)]
+struct SatelliteInfo<'a> {
+    id: &'a str,
+    orbit: u64,
+    eci: StateInfo,
+    ecef: StateInfo,
+    geodetic: GeodeticInfo,
+    elements: Option<ElementsInfo>,
+    beta_angle: Option<f64>, // (degrees)
+    propagator: &'static str,
+    connections: Vec<&'a str>,
+}
+
+type SatelliteQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Name,
+        &'static Satellite,
+        &'static Transform,
+        &'static Velocity,
+        &'static Following,
+        &'static Connections,
+        Option<&'static Sgp4Propagator>,
+    ),
+>;
+
+/// The state of a satellite at the current clock time, or `None` outside the region.
+fn satellite_info<'a>(
+    (name, satellite, transform, velocity, following, conns, sgp4): (
+        &'a Name,
+        &Satellite,
+        &Transform,
+        &Velocity,
+        &Following,
+        &Connections,
+        Option<&Sgp4Propagator>,
+    ),
+    clock: &SimulationClock,
+    ephemeris: &Ephemeris,
+    region: Option<&Region>,
+    orbits: &Query<&Orbit>,
+    names: &'a Query<&Name>,
+) -> Option<SatelliteInfo<'a>> {
+    let geodetic = transform_to_geodetic(transform, clock);
+    if region.is_some_and(|region| !region.contains(&geodetic)) {
+        return None;
+    }
+    let orbit = orbits.get(following.get()).ok();
+    let elements = orbit.map(|orbit| ElementsInfo {
+        mean_motion: orbit.mean_motion as f64 * 86400.0 / std::f64::consts::TAU,
+        eccentricity: orbit.eccentricity as f64,
+        inclination: (orbit.inclination as f64).to_degrees(),
+        ra_of_asc_node: (orbit.longitude_of_ascending_node as f64)
+            .rem_euclid(std::f64::consts::TAU)
+            .to_degrees(),
+        arg_of_pericenter: (orbit.argument_of_periapsis as f64)
+            .rem_euclid(std::f64::consts::TAU)
+            .to_degrees(),
+        mean_anomaly: (satellite.mean_anomaly as f64).to_degrees(),
+    });
+    Some(SatelliteInfo {
+        id: name.as_str(),
+        orbit: following.get().to_bits(),
+        eci: StateInfo {
+            position: transform.translation.as_dvec3(),
+            velocity: velocity.0.as_dvec3(),
+        },
+        ecef: StateInfo {
+            position: transform_to_ecef(transform, clock),
+            velocity: eci_to_ecef_velocity(transform.translation, velocity.0, clock.now()),
+        },
+        geodetic: GeodeticInfo {
+            latitude: geodetic.latitude.to_degrees(),
+            longitude: geodetic.longitude.to_degrees(),
+            altitude: geodetic.altitude,
+        },
+        elements,
+        beta_angle: orbit.map(|orbit| ephemeris.beta_angle(orbit).to_degrees()),
+        propagator: sgp4.map_or("kepler", |sgp4| sgp4.name()),
+        connections: conns
+            .satellites()
+            .iter()
+            .filter_map(|&other| names.get(other).ok())
+            .map(|name| name.as_str())
+            .collect(),
+    })
+}
+
+/// Get the state of satellites by their IDs, an ID prefix or a region. Filters combine,
+/// and all satellites are returned without any.
+///
+/// # Parameters
+/// - ids: [String, .. ] - Optional, the IDs (OBJECT_ID) of the satellites.
+/// - prefix: String - Optional, the prefix of the IDs, e.g. "2019-074".
+/// - region: { min_latitude: Number, max_latitude: Number, min_longitude: Number,
+///   max_longitude: Number, min_altitude: Number, max_altitude: Number } - Optional,
+///   geodetic bounds in degrees and km, each optional.
+///
+/// # Returns
+/// - [ { id: String, orbit: Number, eci: { position: [Number; 3], velocity: [Number; 3] },
+///   ecef: { position: [Number; 3], velocity: [Number; 3] },
+///   geodetic: { latitude: Number, longitude: Number, altitude: Number },
+///   elements: { mean_motion: Number, eccentricity: Number, inclination: Number,
+///   ra_of_asc_node: Number, arg_of_pericenter: Number, mean_anomaly: Number },
+///   beta_angle: Number, propagator: String, connections: [String, .. ] }, .. ]
+///   The ID of the parent orbit, positions in km and velocities in km/s.
+///   Elements of the parent orbit in rev/day and
+///   degrees, as in OMM records. The beta angle of the parent orbit in degrees, positive
+///   with the Sun on the side of the orbit normal. The propagator is "kepler", "sgp4" or
+///   "sdp4" for deep space element sets. The IDs of the satellites linked by ISLs.
+fn get_satellites(
+    In(params): In<Option<Value>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    satellites: SatelliteQuery,
+    orbits: Query<&Orbit>,
+    names: Query<&Name>,
+) -> BrpResult<Value> {
+    let SatellitesParams {
+        ids,
+        prefix,
+        region,
+    } = match params {
+        Some(params) => parse(params)?,
+        None => default(),
+    };
+    let ids: Option<HashSet<&str>> = ids
+        .as_ref()
+        .map(|ids| ids.iter().map(String::as_str).collect());
+    let infos: Vec<_> = satellites
+        .iter()
+        .filter(|(name, ..)| {
+            ids.as_ref().is_none_or(|ids| ids.contains(name.as_str()))
+                && prefix
+                    .as_ref()
+                    .is_none_or(|prefix| name.starts_with(prefix.as_str()))
+        })
+        .filter_map(|item| {
+            satellite_info(item, &clock, &ephemeris, region.as_ref(), &orbits, &names)
+        })
+        .collect();
+    serde_json::to_value(infos).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct SatelliteParams {
+    id: String,
+}
+
+/// Get the state of one satellite by its ID.
+///
+/// # Parameters
+/// - id: String - The ID (OBJECT_ID) of the satellite.
+///
+/// # Returns
+/// - The same as an item of `get_satellites`.
+fn get_satellite(
+    In(params): In<Option<Value>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    satellites: SatelliteQuery,
+    orbits: Query<&Orbit>,
+    names: Query<&Name>,
+) -> BrpResult<Value> {
+    let SatelliteParams { id } = parse_some(params)?;
+    let item = satellites
         .iter()
-        .map(|AddSatelliteParams { id, elements }| {
-            let satellite = OrbitalElements::from_slice(elements).map_err(|err| BrpError {
+        .find(|(name, ..)| name.as_str() == id)
+        .ok_or_else(|| BrpError {
+            code: error_codes::INVALID_PARAMS,
+            message: format!("{} not found", id),
+            data: None,
+        })?;
+    let info = satellite_info(item, &clock, &ephemeris, None, &orbits, &names);
+    serde_json::to_value(info).map_err(BrpError::internal)
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveSatellitesParams {
+    ids: Vec<String>,
+}
+
+/// Remove satellites and drop their links. Orbits left without satellites are removed too.
+/// Applied at the next fixed step.
+///
+/// # Parameters
+/// - ids: [String, .. ] - The IDs of the satellites. Nothing is removed if any is not found.
+fn remove_satellites(
+    In(params): In<Option<Value>>,
+    satellites: Query<(Entity, &Name), With<Satellite>>,
+    mut events: EventWriter<RemoveSatellites>,
+) -> BrpResult<Value> {
+    let RemoveSatellitesParams { ids } = parse_some(params)?;
+    let entities: HashMap<&str, Entity> = satellites
+        .iter()
+        .map(|(entity, name)| (name.as_str(), entity))
+        .collect();
+    let missing: Vec<&str> = ids
+        .iter()
+        .map(String::as_str)
+        .filter(|id| !entities.contains_key(id))
+        .collect();
+    if !missing.is_empty() {
+        return Err(BrpError {
+            code: error_codes::INVALID_PARAMS,
+            message: format!("Satellites not found: {}", missing.join(", ")),
+            data: None,
+        });
+    }
+    events.write(RemoveSatellites {
+        satellites: ids.iter().map(|id| entities[id.as_str()]).collect(),
+    });
+    BrpResult::Ok(Value::Null)
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveOrbitsParams {
+    ids: Vec<u64>,
+}
+
+/// Remove orbits with all their satellites. Applied at the next fixed step.
+///
+/// # Parameters
+/// - ids: [Number, .. ] - The IDs of the orbits, as `orbit` in `get_satellites`.
+///   Nothing is removed if any is not found.
+fn remove_orbits(
+    In(params): In<Option<Value>>,
+    orbits: Query<(), With<Orbit>>,
+    mut events: EventWriter<RemoveOrbits>,
+) -> BrpResult<Value> {
+    let RemoveOrbitsParams { ids } = parse_some(params)?;
+    let mut entities = vec![];
+    for id in ids {
+        let entity = Entity::try_from_bits(id)
+            .ok()
+            .filter(|entity| orbits.contains(*entity))
+            .ok_or_else(|| BrpError {
                 code: error_codes::INVALID_PARAMS,
-                message: err,
+                message: format!("Orbit {} not found", id),
                 data: None,
             })?;
-            Ok((id.clone(), satellite))
-        })
-        .collect::<BrpResult<Vec<(String, OrbitalElements)>>>()?;
-    event.write(SpawnSatellites { satellites });
+        entities.push(entity);
+    }
+    events.write(RemoveOrbits { orbits: entities });
     BrpResult::Ok(Value::Null)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::test_app;
+    use serde_json::json;
+
+    const TEST_CONFIG: &str = r#"
+        [Network]
+        port = 0
+        [Display]
+        orbit = false
+        connection = false
+        [Simulation]
+        start_time = "2025-05-01T00:00:00"
+        time_speed = 1.0
+        connection_distance = 5000.0
+        connection_number = 4
+    "#;
+
+    /// A - B - C, and A - D, with B at the origin
+    fn nodes() -> Vec<TopologyNode<'static>> {
+        let node = |id, position, neighbours: &[usize]| TopologyNode {
+            id,
+            position,
+            neighbours: neighbours.to_vec(),
+        };
+        vec![
+            node("A", Vec3::new(3.0, 0.0, 0.0), &[1, 3]),
+            node("B", Vec3::ZERO, &[2, 0]),
+            node("C", Vec3::new(0.0, 4.0, 0.0), &[1]),
+            node("D", Vec3::new(3.0, 0.0, 2.0), &[0]),
+        ]
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let nodes = nodes();
+        let params = TopologyParams {
+            format: TopologyFormat::Adjacency,
+            distances: true,
+            positions: false,
+        };
+        let topology = adjacency(&nodes, &params);
+        let ids: Vec<&str> = topology.iter().map(|info| info.id).collect();
+        assert_eq!(ids, ["A", "B", "C", "D"]);
+        assert_eq!(topology[1].neighbours, ["C", "A"]);
+        assert_eq!(topology[1].distances, Some(vec![4.0, 3.0]));
+        assert_eq!(topology[3].neighbours, ["A"]);
+        assert!(topology.iter().all(|info| info.position.is_none()));
+    }
+
+    #[test]
+    fn test_edge_list() {
+        let nodes = nodes();
+        let params = TopologyParams {
+            format: TopologyFormat::Edges,
+            distances: true,
+            positions: true,
+        };
+        let topology = edge_list(&nodes, &params);
+        assert_eq!(topology.nodes, ["A", "B", "C", "D"]);
+        // each link once, from the lower index
+        assert_eq!(topology.edges, [[0, 1], [0, 3], [1, 2]]);
+        assert_eq!(topology.distances, Some(vec![3.0, 2.0, 4.0]));
+        // positions by node index
+        let positions = topology.positions.unwrap();
+        for (i, node) in nodes.iter().enumerate() {
+            assert_eq!(topology.nodes[i], node.id);
+            assert_eq!(positions[i], node.position);
+        }
+    }
+
+    #[test]
+    fn test_region_contains() {
+        let at = |latitude: f64, longitude: f64, altitude: f64| Geodetic {
+            latitude: latitude.to_radians(),
+            longitude: longitude.to_radians(),
+            altitude,
+        };
+        let region = Region {
+            min_latitude: Some(30.0),
+            max_latitude: Some(60.0),
+            min_longitude: Some(-10.0),
+            max_longitude: Some(40.0),
+            max_altitude: Some(600.0),
+            ..default()
+        };
+        assert!(region.contains(&at(45.0, 0.0, 550.0)));
+        assert!(region.contains(&at(31.0, 39.0, 599.0)));
+        assert!(!region.contains(&at(29.0, 0.0, 550.0)));
+        assert!(!region.contains(&at(45.0, 41.0, 550.0)));
+        assert!(!region.contains(&at(45.0, 0.0, 1200.0)));
+
+        // across the antimeridian, from 170°E to 170°W
+        let region = Region {
+            min_longitude: Some(170.0),
+            max_longitude: Some(-170.0),
+            ..default()
+        };
+        assert!(region.contains(&at(0.0, 175.0, 550.0)));
+        assert!(region.contains(&at(0.0, -175.0, 550.0)));
+        assert!(region.contains(&at(0.0, 180.0, 550.0)));
+        assert!(!region.contains(&at(0.0, 0.0, 550.0)));
+        assert!(!region.contains(&at(0.0, 160.0, 550.0)));
+
+        assert!(Region::default().contains(&at(-90.0, -180.0, 0.0)));
+    }
+
+    #[test]
+    fn test_set_routing() {
+        let mut app = test_app(TEST_CONFIG, 1);
+        let world = app.world_mut();
+        let mut set_routing = |params: Value| {
+            world
+                .run_system_cached_with(set_routing, Some(params))
+                .unwrap()
+        };
+        let err = set_routing(json!({"k": 5})).unwrap_err();
+        assert_eq!(err.code, error_codes::INVALID_PARAMS);
+        assert!(set_routing(json!({"algorithm": "latency", "k": 5})).is_err());
+        set_routing(json!({"algorithm": "k_shortest_paths", "k": 5})).unwrap();
+        assert_eq!(
+            world.resource::<Routing>().algorithm(),
+            "k_shortest_paths(k=5)"
+        );
+    }
+
+    #[test]
+    fn test_set_offload_policy() {
+        let mut app = test_app(TEST_CONFIG, 1);
+        let world = app.world_mut();
+        let mut set_offload_policy = |params: Value| {
+            world
+                .run_system_cached_with(set_offload_policy, Some(params))
+                .unwrap()
+        };
+        let err = set_offload_policy(json!({"policy": "local", "k": 5})).unwrap_err();
+        assert_eq!(err.code, error_codes::INVALID_PARAMS);
+        assert!(set_offload_policy(json!({"policy": "external", "k": 5})).is_err());
+        set_offload_policy(json!({"policy": "least_loaded", "k": 5})).unwrap();
+        assert_eq!(world.resource::<Offloading>().policy(), "least_loaded(k=5)");
+    }
+
+    #[test]
+    fn test_attach_to_spawning_orbit() {
+        let mut app = test_app(TEST_CONFIG, 1);
+        // the first update only starts the clocks
+        app.update();
+
+        // the orbit is only reserved until the next fixed step
+        let world = app.world_mut();
+        let ids = world
+            .run_system_cached_with(
+                add_orbits,
+                Some(json!({"orbits": [{
+                    "apogee": 6921.0,
+                    "perigee": 6921.0,
+                    "inclination": 53.0,
+                    "longitude_of_ascending_node": 0.0,
+                    "argument_of_periapsis": 0.0,
+                    "units": {"angle": "deg"},
+                }]})),
+            )
+            .unwrap()
+            .unwrap();
+        let orbit = ids[0].as_u64().unwrap();
+        world
+            .run_system_cached_with(
+                attach_satellites,
+                Some(json!({"orbit": orbit, "satellites": [
+                    {"id": "P0-0", "mean_anomaly": 0.0},
+                    {"id": "P0-1", "mean_anomaly": 180.0, "units": {"angle": "deg"}},
+                ]})),
+            )
+            .unwrap()
+            .unwrap();
+        app.update();
+
+        let orbit = Entity::from_bits(orbit);
+        let world = app.world_mut();
+        assert!(world.get::<Orbit>(orbit).is_some());
+        let mut followers: Vec<(Entity, String)> = world
+            .query::<(Entity, &Name, &Following)>()
+            .iter(world)
+            .filter(|(_, _, following)| following.get() == orbit)
+            .map(|(entity, name, _)| (entity, name.to_string()))
+            .collect();
+        followers.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(followers.len(), 2);
+        assert_eq!(followers[0].1, "P0-0");
+        let followed_by: Vec<Entity> = world
+            .get::<<Following as Relationship>::RelationshipTarget>(orbit)
+            .unwrap()
+            .iter()
+            .collect();
+        assert_eq!(followed_by.len(), 2);
+        assert!(followers
+            .iter()
+            .all(|(entity, _)| followed_by.contains(entity)));
+    }
+}
@@ -4,6 +4,7 @@ mod prelude {
 
     pub use super::config::*;
     pub use super::core::*;
+    pub use super::headless::Headless;
     pub use super::utils::*;
 
     pub use std::f32::consts::PI;
@@ -15,35 +16,86 @@ use prelude::*;
 
 use camera::OrbitCameraPlugin;
 use core::CorePlugin;
+use headless::HeadlessPlugin;
 use ui::UserInterfacePlugin;
 
 mod camera;
 mod config;
 mod core;
+mod headless;
 mod io;
+mod tle;
 mod ui;
 mod utils;
 
+/// Command line options.
+///
+/// - `--config <path>`: The config file, defaults to `config.toml` in the working directory.
+/// - `--headless`: Run without a window, as fast as possible.
+/// - `--realtime`: With `--headless`, run at the speed of the wall clock instead.
+/// - `--duration <seconds>`: With `--headless`, exit after this much simulation time.
+struct Args {
+    config: Option<String>,
+    headless: bool,
+    realtime: bool,
+    duration: Option<f64>,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Args {
+            config: None,
+            headless: false,
+            realtime: false,
+            duration: None,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => args.config = Some(iter.next().ok_or("Missing config path")?),
+                "--headless" => args.headless = true,
+                "--realtime" => args.realtime = true,
+                "--duration" => {
+                    args.duration = Some(iter.next().ok_or("Missing duration")?.parse()?)
+                }
+                _ => return Err(format!("Unknown argument: {}", arg).into()),
+            }
+        }
+        if !args.headless && (args.realtime || args.duration.is_some()) {
+            return Err("--realtime and --duration require --headless".into());
+        }
+        Ok(args)
+    }
+}
+
 fn main() -> Result {
-    let mut config_path = std::env::current_dir()?;
-    config_path.push("config.toml");
+    let args = Args::parse()?;
+    let config_path = match args.config {
+        Some(path) => path.into(),
+        None => std::env::current_dir()?.join("config.toml"),
+    };
     println!("Loading Config file... : {:?}", config_path);
     let config = config::Config::load(&config_path)?;
 
     let port = config.network.port;
 
-    App::new()
-        .insert_resource(config)
-        .add_plugins(DefaultPlugins)
-        .add_plugins((
-            OrbitCameraPlugin,
-            CorePlugin,
-            io::IOPlugin::new(port),
-            UserInterfacePlugin,
-            FrameTimeDiagnosticsPlugin::default(),
-        ))
-        .add_systems(Startup, setup)
-        .run();
+    let mut app = App::new();
+    app.insert_resource(config);
+    if args.headless {
+        app.add_plugins(HeadlessPlugin {
+            duration: args.duration,
+            realtime: args.realtime,
+        });
+    } else {
+        app.add_plugins(DefaultPlugins)
+            .add_plugins((
+                OrbitCameraPlugin,
+                UserInterfacePlugin,
+                FrameTimeDiagnosticsPlugin::default(),
+            ))
+            .add_systems(Startup, setup);
+    }
+    app.add_plugins((CorePlugin, io::IOPlugin::new(port))).run();
     Ok(())
 }
 
@@ -54,17 +106,39 @@ fn setup(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut gizmo_assets: ResMut<Assets<GizmoAsset>>,
 ) {
-    // 创建一个蓝色材质表示地球
+    // 创建一个蓝色材质表示地球, 由太阳光照亮以显示昼夜
     let earth_material = materials.add(StandardMaterial {
         base_color: Color::srgb(0.0, 0.3, 0.7),
-        unlit: true, // 无光照
+        perceptual_roughness: 0.8,
         ..Default::default()
     });
 
-    // 创建一个球体并设置其位置在原点
+    // 太阳光, 方向随仿真时间更新; 环境光使夜半球仍可见
+    commands.spawn(Sunlight);
+    commands.insert_resource(AmbientLight {
+        brightness: 100.0,
+        ..default()
+    });
+
+    // 本初子午线, 用于显示地球自转
+    let mut meridian = GizmoAsset::default();
+    meridian.linestrip(
+        (0..=36).map(|i| {
+            let latitude = (i as f32 * 5.0 - 90.0).to_radians();
+            1.001 * EARTH_RADIUS * Vec3::new(latitude.cos(), 0.0, latitude.sin())
+        }),
+        Color::WHITE,
+    );
+
+    // 创建一个球体并设置其位置在原点, 随地球自转
     commands.spawn((
+        Earth,
         Mesh3d(meshes.add(Sphere::new(EARTH_RADIUS).mesh().uv(32, 18))),
         MeshMaterial3d(earth_material),
+        children![Gizmo {
+            handle: gizmo_assets.add(meridian),
+            ..default()
+        }],
     ));
 
     // 创建坐标轴
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+
+use crate::prelude::*;
+
+/// Parse a TLE (2 lines per satellite) or 3LE (with a name line) file.
+///
+/// Example of 3LE:
+/// ```text
+/// ISS (ZARYA)
+/// 1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927
+/// 2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537
+/// ```
+pub fn parse_tle(content: &str) -> Result<Vec<RawSatelliteData>, String> {
+    let lines: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    let mut satellites = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let (line_no, line) = lines[i];
+        if line.starts_with("2 ") {
+            return Err(format!("Line {}: line 2 of TLE without line 1", line_no));
+        }
+        if !line.starts_with("1 ") {
+            // name line of 3LE, only before a line 1
+            if !lines
+                .get(i + 1)
+                .is_some_and(|(_, next)| next.starts_with("1 "))
+            {
+                return Err(format!(
+                    "Line {}: expected a TLE line or a name followed by line 1, found {:?}",
+                    line_no, line
+                ));
+            }
+            i += 1;
+            continue;
+        }
+        let Some(&(_, line2)) = lines.get(i + 1) else {
+            return Err(format!("Line {}: missing the second line of TLE", line_no));
+        };
+        let satellite =
+            parse_tle_lines(line, line2).map_err(|err| format!("Line {}: {}", line_no, err))?;
+        satellites.push(satellite);
+        i += 2;
+    }
+
+    if satellites.is_empty() {
+        return Err("No TLE found".to_string());
+    }
+    Ok(satellites)
+}
+
+/// Parse one pair of TLE lines.
+fn parse_tle_lines(line1: &str, line2: &str) -> Result<RawSatelliteData, String> {
+    for (number, line) in [('1', line1), ('2', line2)] {
+        if !line.is_ascii() || line.len() < 69 {
+            return Err(format!("line {} should have 69 ascii characters", number));
+        }
+        if !line.starts_with(number) {
+            return Err(format!("line {} should start with '{}'", number, number));
+        }
+        let checksum = tle_checksum(line);
+        if line[68..69] != checksum.to_string() {
+            return Err(format!(
+                "checksum of line {} mismatch, expected {}, found {}",
+                number,
+                checksum,
+                &line[68..69]
+            ));
+        }
+    }
+    if line1[2..7] != line2[2..7] {
+        return Err("catalog numbers of the two lines mismatch".to_string());
+    }
+
+    // line 1
+    let epoch_year: i32 = parse_field(line1, 18..20, "epoch year")?;
+    let epoch_day: f64 = parse_field(line1, 20..32, "epoch day")?;
+    let bstar = parse_exponent(&line1[53..61], "BSTAR")?;
+
+    // line 2
+    let inclination: f64 = parse_field(line2, 8..16, "inclination")?;
+    let ra_of_asc_node: f64 = parse_field(line2, 17..25, "right ascension of ascending node")?;
+    // decimal point assumed
+    let eccentricity = parse_field::<u32>(line2, 26..33, "eccentricity")? as f64 / 1e7;
+    let arg_of_pericenter: f64 = parse_field(line2, 34..42, "argument of perigee")?;
+    let mean_anomaly: f64 = parse_field(line2, 43..51, "mean anomaly")?;
+    let mean_motion: f64 = parse_field(line2, 52..63, "mean motion")?;
+
+    let epoch = epoch_from_tle(epoch_year, epoch_day)?;
+
+    Ok(RawSatelliteData {
+        object_id: object_id_from_tle(line1),
+        epoch: epoch.format("%Y-%m-%dT%H:%M:%S%.6f").to_string(),
+        mean_motion,
+        eccentricity,
+        inclination,
+        ra_of_asc_node,
+        arg_of_pericenter,
+        mean_anomaly,
+        bstar,
+    })
+}
+
+/// Modulo 10 checksum: sum of all digits, minus signs count as 1.
+fn tle_checksum(line: &str) -> u32 {
+    line[..68]
+        .chars()
+        .map(|c| match c {
+            '-' => 1,
+            c => c.to_digit(10).unwrap_or(0),
+        })
+        .sum::<u32>()
+        % 10
+}
+
+fn parse_field<T: std::str::FromStr>(
+    line: &str,
+    range: std::ops::Range<usize>,
+    name: &str,
+) -> Result<T, String> {
+    let field = line[range].trim();
+    field
+        .parse()
+        .map_err(|_| format!("invalid {}: {:?}", name, field))
+}
+
+/// Parse fields like " 28098-4" or "-11606-4", which means 0.28098e-4 and -0.11606e-4.
+fn parse_exponent(field: &str, name: &str) -> Result<f64, String> {
+    let field = field.trim();
+    let invalid = || format!("invalid {}: {:?}", name, field);
+    if field.len() < 2 {
+        return Err(invalid());
+    }
+    let (mantissa, exponent) = field.split_at(field.len() - 2);
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => (-1.0, mantissa),
+        None => (1.0, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let mantissa: f64 = format!("0.{}", mantissa).parse().map_err(|_| invalid())?;
+    let exponent: i32 = exponent.parse().map_err(|_| invalid())?;
+    Ok(sign * mantissa * 10f64.powi(exponent))
+}
+
+/// Two-digit years: 57-99 are 1957-1999, 00-56 are 2000-2056.
+fn epoch_from_tle(year: i32, day_of_year: f64) -> Result<DateTime<Utc>, String> {
+    let year = if year < 57 { 2000 + year } else { 1900 + year };
+    let days = if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+        366.0
+    } else {
+        365.0
+    };
+    if !(1.0..days + 1.0).contains(&day_of_year) {
+        return Err(format!("invalid epoch day: {}", day_of_year));
+    }
+    let start = Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap();
+    Ok(start + Duration::microseconds(((day_of_year - 1.0) * 86_400e6).round() as i64))
+}
+
+/// International designator "98067A" to "1998-067A", the same as OMM `OBJECT_ID`.
+/// Falls back to the catalog number when the designator is blank.
+fn object_id_from_tle(line1: &str) -> String {
+    let designator = line1[9..17].trim();
+    match designator
+        .get(..2)
+        .and_then(|year| year.parse::<i32>().ok())
+    {
+        Some(year) if designator.len() > 2 => {
+            let year = if year < 57 { 2000 + year } else { 1900 + year };
+            format!("{}-{}", year, &designator[2..])
+        }
+        _ => line1[2..7].trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VANGUARD: &str = "\
+1 00005U 58002B   00179.78495062  .00000023  00000-0  28098-4 0  4753
+2 00005  34.2682 348.7242 1859667 331.7664  19.3264 10.82419157413667";
+
+    const ISS: &str = "\
+ISS (ZARYA)
+1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927
+2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537
+";
+
+    #[test]
+    fn test_parse_tle() {
+        let satellites = parse_tle(VANGUARD).unwrap();
+        assert_eq!(satellites.len(), 1);
+        let sat = &satellites[0];
+        assert_eq!(sat.object_id, "1958-002B");
+        assert!(sat.epoch.starts_with("2000-06-27T18:50:19.73"));
+        assert_eq!(sat.eccentricity, 0.1859667);
+        assert_eq!(sat.mean_motion, 10.82419157);
+        assert!((sat.bstar - 0.28098e-4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_3le() {
+        let satellites = parse_tle(ISS).unwrap();
+        assert_eq!(satellites.len(), 1);
+        let sat = &satellites[0];
+        assert_eq!(sat.object_id, "1998-067A");
+        assert!(sat.epoch.starts_with("2008-09-20T12:25:40"));
+        assert!((sat.bstar + 0.11606e-4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let broken = ISS.replace("0  2927", "0  2928");
+        let err = parse_tle(&broken).unwrap_err();
+        assert!(err.contains("checksum"), "{}", err);
+    }
+
+    #[test]
+    fn test_orphan_and_garbage_lines() {
+        let line2 = ISS.lines().nth(2).unwrap();
+        let err = parse_tle(&format!("{}{}", ISS, line2)).unwrap_err();
+        assert!(err.starts_with("Line 4:"), "{}", err);
+        assert!(err.contains("without line 1"), "{}", err);
+
+        // a name must be followed by line 1
+        let err = parse_tle(&format!("{}not a TLE\n", ISS)).unwrap_err();
+        assert!(err.starts_with("Line 4:"), "{}", err);
+        let err = parse_tle(&format!("garbage\n{}", ISS)).unwrap_err();
+        assert!(err.starts_with("Line 1:"), "{}", err);
+
+        assert!(parse_tle("").is_err());
+        assert!(parse_tle("\n\n").is_err());
+    }
+
+    #[test]
+    fn test_epoch_day() {
+        assert!(epoch_from_tle(0, 366.5).is_ok());
+        assert!(epoch_from_tle(24, 366.5).is_ok());
+        assert!(epoch_from_tle(25, 366.5).is_err());
+        assert!(epoch_from_tle(25, 365.9).is_ok());
+        assert!(epoch_from_tle(25, 0.5).is_err());
+    }
+}
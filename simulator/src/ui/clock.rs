@@ -0,0 +1,34 @@
+use bevy::color::palettes::css::GOLD;
+
+use crate::prelude::*;
+
+#[derive(Component)]
+pub struct ClockText;
+
+pub fn clock() -> impl Bundle {
+    (
+        Text::new("Time: "),
+        TextFont::from_font_size(18.0),
+        children![(
+            TextSpan::default(),
+            TextColor(GOLD.into()),
+            ClockText,
+            TextFont::from_font_size(18.0),
+        )],
+    )
+}
+
+pub fn clock_system(clock: Res<SimulationClock>, mut texts: Query<&mut TextSpan, With<ClockText>>) {
+    for mut span in &mut texts {
+        let state = if clock.is_paused() {
+            "paused".to_string()
+        } else {
+            format!("x{}", clock.time_speed())
+        };
+        **span = format!(
+            "{} ({})",
+            clock.now().format("%Y-%m-%d %H:%M:%S UTC"),
+            state
+        );
+    }
+}
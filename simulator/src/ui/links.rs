@@ -0,0 +1,50 @@
+use bevy::color::palettes::css::GOLD;
+
+use crate::prelude::*;
+
+#[derive(Component)]
+pub struct LinkStatsText;
+
+pub fn link_stats() -> impl Bundle {
+    (
+        Text::new("Links: "),
+        TextFont::from_font_size(18.0),
+        children![(
+            TextSpan::default(),
+            TextColor(GOLD.into()),
+            LinkStatsText,
+            TextFont::from_font_size(18.0),
+        )],
+    )
+}
+
+pub fn link_stats_system(
+    clock: Res<SimulationClock>,
+    log: Res<LinkLog>,
+    satellites: Query<Entity, With<Satellite>>,
+    mut texts: Query<&mut TextSpan, With<LinkStatsText>>,
+) {
+    if !log.is_changed() {
+        return;
+    }
+    let stats = log.stats(clock.elapsed_secs(), satellites.iter());
+    let mean_duration = stats
+        .mean_duration
+        .map_or("-".to_string(), |duration| format!("{:.0} s", duration));
+    let degrees: Vec<String> = stats
+        .degree_histogram
+        .iter()
+        .map(|count| count.to_string())
+        .collect();
+    for mut span in &mut texts {
+        **span = format!(
+            "{} ISLs, {} ground\nmean duration {}, up {:.2}/s, down {:.2}/s\ndegrees [{}]",
+            stats.active_isls,
+            stats.active_ground_links,
+            mean_duration,
+            stats.established_per_second,
+            stats.torn_down_per_second,
+            degrees.join(", "),
+        );
+    }
+}
@@ -2,12 +2,16 @@ use bevy::{
     color::palettes::tailwind::{SKY_700, SLATE_50},
     ecs::{relationship::RelatedSpawner, spawn::SpawnWith},
 };
+use clock::clock;
 use fps::fps;
+use links::link_stats;
 
 use crate::{core::ToggleOrbitGizmos, prelude::*};
 
+mod clock;
 mod display_toggle;
 mod fps;
+mod links;
 // mod widgets;
 
 pub struct UserInterfacePlugin;
@@ -15,7 +19,14 @@ pub struct UserInterfacePlugin;
 impl Plugin for UserInterfacePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup);
-        app.add_systems(Update, fps::fps_system);
+        app.add_systems(
+            Update,
+            (
+                fps::fps_system,
+                clock::clock_system,
+                links::link_stats_system,
+            ),
+        );
     }
 }
 
@@ -29,6 +40,9 @@ fn setup(mut commands: Commands, _config: Res<Config>) {
         },
         Children::spawn(SpawnWith(|parent: &mut RelatedSpawner<ChildOf>| {
             parent.spawn(fps());
+            parent.spawn(clock());
+            parent.spawn(link_stats());
+            parent.spawn(clock_buttons());
             parent.spawn(button("Toggle Connection")).observe(
                 |_trigger: Trigger<Pointer<Click>>, mut config: ResMut<Config>| {
                     config.display.connection = !config.display.connection;
@@ -44,10 +58,55 @@ fn setup(mut commands: Commands, _config: Res<Config>) {
                     commands.trigger(ToggleOrbitGizmos);
                 },
             );
+            parent.spawn(button("Toggle Battery Color")).observe(
+                |_trigger: Trigger<Pointer<Click>>, mut config: ResMut<Config>| {
+                    config.display.color_mode = match config.display.color_mode {
+                        ColorMode::Default => ColorMode::StateOfCharge,
+                        ColorMode::StateOfCharge => ColorMode::Default,
+                    };
+                    info!("Color Mode: {:?}", config.display.color_mode);
+                },
+            );
         })),
     ));
 }
 
+/// Pause, single-step and time warp controls of the simulation clock.
+fn clock_buttons() -> impl Bundle {
+    (
+        Node {
+            column_gap: Val::Px(5.),
+            ..default()
+        },
+        Children::spawn(SpawnWith(|parent: &mut RelatedSpawner<ChildOf>| {
+            parent.spawn(button("Pause")).observe(
+                |_trigger: Trigger<Pointer<Click>>, mut clock: ResMut<SimulationClock>| {
+                    let paused = !clock.is_paused();
+                    clock.set_paused(paused);
+                    info!("Pause: {}", paused);
+                },
+            );
+            parent.spawn(button("Step")).observe(
+                |_trigger: Trigger<Pointer<Click>>, mut clock: ResMut<SimulationClock>| {
+                    clock.step();
+                },
+            );
+            parent.spawn(button("Slower")).observe(
+                |_trigger: Trigger<Pointer<Click>>, mut clock: ResMut<SimulationClock>| {
+                    let time_speed = clock.time_speed() / 2.;
+                    clock.set_time_speed(time_speed);
+                },
+            );
+            parent.spawn(button("Faster")).observe(
+                |_trigger: Trigger<Pointer<Click>>, mut clock: ResMut<SimulationClock>| {
+                    let time_speed = clock.time_speed() * 2.;
+                    clock.set_time_speed(time_speed);
+                },
+            );
+        })),
+    )
+}
+
 fn button<T: Into<String>>(text: T) -> impl Bundle {
     (
         Button,
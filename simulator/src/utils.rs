@@ -39,23 +39,52 @@ pub fn anomaly_eccentric_to_true(anm_ecc: f32, e: f32) -> f32 {
 }
 
 pub fn parse_time_from_str(time_str: &str) -> Result<DateTime<Utc>, String> {
-    // 2024-10-27T04:10:58.101312
-    let naive_datetime = NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M:%S%.6f");
+    // 2024-10-27T04:10:58.101312, fractional seconds and a trailing "Z" are optional
+    let time_str = time_str.strip_suffix('Z').unwrap_or(time_str);
+    let naive_datetime = NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M:%S%.f");
     match naive_datetime {
         Ok(naive_datetime) => Ok(naive_datetime.and_utc()),
         Err(e) => Err(e.to_string()),
     }
 }
 
+/// Whether the segment between `a` and `b` stays outside the sphere of `radius` at the origin.
+pub fn line_of_sight(a: Vec3, b: Vec3, radius: f32) -> bool {
+    let ab = b - a;
+    // closest point of the segment to the origin
+    let t = if ab.length_squared() > 0.0 {
+        (-a.dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + t * ab).length_squared() > radius * radius
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_line_of_sight() {
+        let radius = EARTH_RADIUS + 100.0;
+        let a = Vec3::new(7000.0, 500.0, 0.0);
+        assert!(line_of_sight(a, Vec3::new(7000.0, -500.0, 0.0), radius));
+        // through the Earth
+        assert!(!line_of_sight(a, Vec3::new(-7000.0, 500.0, 0.0), radius));
+        // grazing the atmosphere
+        assert!(!line_of_sight(
+            Vec3::new(6421.0, 1000.0, 0.0),
+            Vec3::new(6421.0, -1000.0, 0.0),
+            radius
+        ));
+    }
+
     #[test]
     fn test_chrono() {
         let date = "2024-10-27T04:10:58.101312";
         let parsed_time = parse_time_from_str(date).unwrap();
         println!("{}", parsed_time);
+        assert!(parse_time_from_str("2024-10-27T04:10:58Z").is_ok());
     }
 }
 
@@ -66,9 +95,9 @@ pub fn get_rotated_quat(
 ) -> Quat {
     let mut quat = Quat::IDENTITY;
 
-    // rotation
-    quat = Quat::from_rotation_x(-inclination) * quat; // rotate_x
+    // rotation: R_z(raan) * R_x(i) * R_z(argp), same frame as SGP4 (TEME, z-up)
+    quat = Quat::from_rotation_x(inclination) * quat; // rotate_x
     quat = Quat::from_rotation_z(longitude_of_ascending_node) * quat; // rotate_z
-    quat *= Quat::from_rotation_z(-argument_of_periapsis); // rotate_local_z
+    quat *= Quat::from_rotation_z(argument_of_periapsis); // rotate_local_z
     quat
 }